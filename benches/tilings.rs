@@ -0,0 +1,58 @@
+//! Time taken to lay out periodic tilings over large frames with small tiles.
+//! Run with `cargo bench`.
+
+use backgen::prelude::*;
+use backgen::tesselate::*;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 5;
+
+type Tiler<'a> = Box<dyn Fn(&mut TileSink) + 'a>;
+
+/// Average time of a run, and number of tiles laid out
+fn bench(tiler: &dyn Fn(&mut TileSink)) -> (Duration, usize) {
+    let mut count = 0;
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        count = 0;
+        tiler(&mut |_, _| count += 1);
+    }
+    (start.elapsed() / RUNS, count)
+}
+
+fn main() {
+    let frames = [(1920, 1080, 15.), (3840, 2160, 10.), (7680, 4320, 5.)];
+    for (w, h, size) in frames {
+        let f = Frame {
+            x: 0,
+            y: 0,
+            w,
+            h,
+            seamless: false,
+        };
+        let tilers: [(&str, Tiler); 6] = [
+            (
+                "hexagons",
+                Box::new(|sink| tile_hexagons(&f, size, 17, sink)),
+            ),
+            ("squares", Box::new(|sink| tile_squares(&f, size, 17, sink))),
+            ("bricks", Box::new(|sink| tile_bricks(&f, size, 17, sink))),
+            ("cubes", Box::new(|sink| tile_cubes(&f, size, 17, sink))),
+            (
+                "archimedean",
+                Box::new(|sink| tile_archimedean(&f, Archimedean::SnubHexagonal, size, 17, sink)),
+            ),
+            (
+                "pentagons",
+                Box::new(|sink| pentagons_type5(&f, size, 17, sink)),
+            ),
+        ];
+        for (name, tiler) in tilers.iter() {
+            let (time, count) = bench(tiler);
+            println!(
+                "{:>4}x{:<4} size {:>4}  {:<12} {:>8} tiles  {:>10.2?}",
+                w, h, size, name, count, time
+            );
+        }
+    }
+}
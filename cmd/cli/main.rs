@@ -1,5 +1,6 @@
-use backgen::gen_image::generate_images;
-use clap::{arg, Parser};
+use backgen::gen_image::{generate_images, stream_svg};
+use clap::Parser;
+use std::{fs::File, io::BufWriter};
 
 // CLI flags configuration
 #[derive(Parser)]
@@ -20,12 +21,37 @@ struct Cli {
     #[arg(default_value = "blur_output.png")]
     #[arg(long, value_name = "blur_output")]
     blur_dest: String,
+
+    /// TOML configuration file (default = built-in options)
+    #[arg(default_value = None)]
+    #[arg(long, value_name = "config")]
+    config: Option<String>,
 }
 
 fn main() {
     let args = Cli::parse();
 
-    match generate_images(args.id, &args.gen_dest, &args.blur_dest) {
+    // Vector output is streamed to disk
+    if args.gen_dest.ends_with(".svg") {
+        let res = File::create(&args.gen_dest)
+            .map_err(|e| e.into())
+            .and_then(|f| {
+                let out = BufWriter::new(f);
+                stream_svg(args.id, args.config.as_deref(), out, &args.blur_dest)
+            });
+        match res {
+            Ok((_, blurhash)) => println!("blurhash is: {blurhash}"),
+            Err(err) => eprintln!("Error occured: {err}"),
+        }
+        return;
+    }
+
+    match generate_images(
+        args.id,
+        args.config.as_deref(),
+        &args.gen_dest,
+        &args.blur_dest,
+    ) {
         Ok(blurhash) => println!("blurhash is: {blurhash}"),
         Err(err) => eprintln!("Error occured: {err}"),
     }
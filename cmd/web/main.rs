@@ -1,18 +1,35 @@
 use axum::{
-    extract::{Path, Query},
+    extract::{Path, Query, State},
     response::Html,
     routing::get,
     Router,
 };
 use backgen::gen_image::generate_images;
+use clap::Parser;
 use minijinja::render;
 use rand::Rng;
-use std::{collections::HashMap, net::SocketAddr};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 use tower_http::services::ServeDir;
 use tracing::Level;
 
+// CLI flags configuration
+#[derive(Parser)]
+#[clap(name = "backgen-web")]
+#[clap(about = "BackGen web server", long_about = None)]
+struct Cli {
+    /// TOML configuration file (default = built-in options)
+    #[arg(default_value = None)]
+    #[arg(long, value_name = "config")]
+    config: Option<String>,
+}
+
+// Configuration file shared by the handlers
+type Config = Arc<Option<String>>;
+
 #[tokio::main]
 async fn main() {
+    let args = Cli::parse();
+
     // initialize tracing
     tracing_subscriber::fmt().with_max_level(Level::INFO).init();
 
@@ -23,7 +40,8 @@ async fn main() {
         // `POST /gen` goes to `gen`
         .route("/gen/:id", get(gen_path_handler))
         .route("/gen", get(gen_query_handler))
-        .nest_service("/assets", ServeDir::new("assets"));
+        .nest_service("/assets", ServeDir::new("assets"))
+        .with_state(Arc::new(args.config));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 5000));
     tracing::info!("listening on {}", addr);
@@ -39,14 +57,17 @@ async fn root() -> Html<&'static str> {
 }
 
 // Generate page from the query like /gen?id=42
-async fn gen_query_handler(Query(params): Query<HashMap<String, String>>) -> Html<String> {
+async fn gen_query_handler(
+    State(config): State<Config>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Html<String> {
     match params.get("id") {
         Some(id) => {
             if id.is_empty() {
-                gen_handler(None).await
+                gen_handler(None, &config).await
             } else {
                 match id.parse::<u64>() {
-                    Ok(id) => gen_handler(Some(id)).await,
+                    Ok(id) => gen_handler(Some(id), &config).await,
                     Err(err) => {
                         tracing::error!("Error occured {err}");
                         Html(format!("Error occured {err}"))
@@ -54,17 +75,17 @@ async fn gen_query_handler(Query(params): Query<HashMap<String, String>>) -> Htm
                 }
             }
         }
-        None => gen_handler(None).await,
+        None => gen_handler(None, &config).await,
     }
 }
 
 // Generate page from the path like /gen/42
-async fn gen_path_handler(Path(id): Path<u64>) -> Html<String> {
-    gen_handler(Some(id)).await
+async fn gen_path_handler(State(config): State<Config>, Path(id): Path<u64>) -> Html<String> {
+    gen_handler(Some(id), &config).await
 }
 
 // Helper which genreate images, blurhash and the final html page.
-async fn gen_handler(id: Option<u64>) -> Html<String> {
+async fn gen_handler(id: Option<u64>, config: &Config) -> Html<String> {
     let id = id.unwrap_or_else(|| {
         let mut rng = rand::thread_rng();
         rng.gen()
@@ -73,7 +94,7 @@ async fn gen_handler(id: Option<u64>) -> Html<String> {
     let root = "assets";
     let gen_dest = format!("{root}/{id}.gen.png");
     let blur_dest = format!("{root}/{id}.blur.png");
-    match generate_images(Some(id), &gen_dest, &blur_dest) {
+    match generate_images(Some(id), config.as_deref(), &gen_dest, &blur_dest) {
         Ok(blurhash) => {
             let r = render!(
                 GEN_PAGE_TEMPLATE,
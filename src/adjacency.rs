@@ -0,0 +1,266 @@
+use crate::prelude::*;
+use crate::svg::Path;
+use std::collections::{HashMap, HashSet};
+
+/// Distance under which points are the same, in pixels
+const TOLERANCE: f64 = 0.01;
+
+/// Part of the outlines of two neighbouring tiles
+#[derive(Debug, Clone, Copy)]
+pub struct SharedEdge {
+    pub tiles: (usize, usize),
+    pub from: Pos,
+    pub to: Pos,
+}
+
+/// Graph of the tiles of a tiling that share a side.
+/// Sides only need to overlap: a corner of a tile can lie in the middle
+/// of a side of its neighbour, as with bricks.
+pub struct Adjacency {
+    neighbours: Vec<Vec<usize>>,
+    edges: Vec<SharedEdge>,
+}
+
+impl Adjacency {
+    /// Find the shared sides of polygons, arcs being flattened beforehand
+    pub fn new(outlines: &[Vec<Pos>]) -> Self {
+        let mut sides = Vec::new();
+        for (tile, pts) in outlines.iter().enumerate() {
+            for (k, &a) in pts.iter().enumerate() {
+                let b = pts[(k + 1) % pts.len()];
+                if a.dist(b) > TOLERANCE {
+                    sides.push((tile, a, b));
+                }
+            }
+        }
+        // Sides are bucketed in a grid, only sides of the same cell are compared
+        let cell =
+            2. * sides.iter().map(|(_, a, b)| a.dist(*b)).sum::<f64>() / sides.len().max(1) as f64;
+        let key = |x: f64| (x / cell).floor() as i64;
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (s, &(_, a, b)) in sides.iter().enumerate() {
+            let (x0, x1) = (key(a.0.min(b.0) - TOLERANCE), key(a.0.max(b.0) + TOLERANCE));
+            let (y0, y1) = (key(a.1.min(b.1) - TOLERANCE), key(a.1.max(b.1) + TOLERANCE));
+            for i in x0..=x1 {
+                for j in y0..=y1 {
+                    grid.entry((i, j)).or_default().push(s);
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        let mut edges = Vec::new();
+        for bucket in grid.values() {
+            for (n, &s) in bucket.iter().enumerate() {
+                for &t in &bucket[n + 1..] {
+                    let ((ts, a, b), (tt, c, d)) = (sides[s], sides[t]);
+                    if ts == tt || !seen.insert((s.min(t), s.max(t))) {
+                        continue;
+                    }
+                    if let Some((from, to)) = overlap(a, b, c, d) {
+                        edges.push(SharedEdge {
+                            tiles: (ts.min(tt), ts.max(tt)),
+                            from,
+                            to,
+                        });
+                    }
+                }
+            }
+        }
+        // Buckets come in no particular order
+        edges.sort_by(|e, f| {
+            e.tiles
+                .cmp(&f.tiles)
+                .then(e.from.0.total_cmp(&f.from.0))
+                .then(e.from.1.total_cmp(&f.from.1))
+        });
+        let mut neighbours = vec![Vec::new(); outlines.len()];
+        for e in &edges {
+            neighbours[e.tiles.0].push(e.tiles.1);
+            neighbours[e.tiles.1].push(e.tiles.0);
+        }
+        for near in &mut neighbours {
+            near.sort_unstable();
+            near.dedup();
+        }
+        Self { neighbours, edges }
+    }
+
+    pub fn from_tiles(tiles: &[(Pos, Path)]) -> Self {
+        let outlines = tiles
+            .iter()
+            .map(|(_, path)| path.data.flatten())
+            .collect::<Vec<_>>();
+        Self::new(&outlines)
+    }
+
+    /// Number of tiles
+    pub fn len(&self) -> usize {
+        self.neighbours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.neighbours.is_empty()
+    }
+
+    /// Tiles that share a side with tile `i`, in increasing order
+    pub fn neighbours(&self, i: usize) -> &[usize] {
+        &self.neighbours[i]
+    }
+
+    /// Shared sides, several per pair of tiles when their outlines bend
+    pub fn edges(&self) -> &[SharedEdge] {
+        &self.edges
+    }
+
+    /// Drop the tiles for which `keep` is false, the others are numbered anew
+    pub fn retain(&mut self, keep: &[bool]) {
+        let mut index = vec![None; keep.len()];
+        let mut next = 0;
+        for (i, &k) in keep.iter().enumerate() {
+            if k {
+                index[i] = Some(next);
+                next += 1;
+            }
+        }
+        let neighbours = std::mem::take(&mut self.neighbours);
+        self.neighbours = neighbours
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, &k)| k)
+            .map(|(near, _)| near.into_iter().filter_map(|j| index[j]).collect())
+            .collect();
+        self.edges
+            .retain_mut(|e| match (index[e.tiles.0], index[e.tiles.1]) {
+                (Some(i), Some(j)) => {
+                    e.tiles = (i, j);
+                    true
+                }
+                _ => false,
+            });
+    }
+
+    /// Make one tile of each group of tiles, `node[i]` being the group of tile `i`.
+    /// Groups are numbered from 0 and share a side when some of their tiles do.
+    pub fn merge(&mut self, node: &[usize]) {
+        let n = node.iter().max().map_or(0, |&k| k + 1);
+        let mut neighbours = vec![Vec::new(); n];
+        for (i, near) in self.neighbours.iter().enumerate() {
+            neighbours[node[i]].extend(near.iter().map(|&j| node[j]).filter(|&k| k != node[i]));
+        }
+        for near in &mut neighbours {
+            near.sort_unstable();
+            near.dedup();
+        }
+        self.neighbours = neighbours;
+        self.edges.retain_mut(|e| {
+            let (i, j) = (node[e.tiles.0], node[e.tiles.1]);
+            e.tiles = (i.min(j), i.max(j));
+            i != j
+        });
+    }
+}
+
+/// Common part of segments `ab` and `cd` when they lie on the same line
+fn overlap(a: Pos, b: Pos, c: Pos, d: Pos) -> Option<(Pos, Pos)> {
+    let len = a.dist(b);
+    let u = (b - a) * (1. / len);
+    let off = |p: Pos| (u.0 * (p.1 - a.1) - u.1 * (p.0 - a.0)).abs();
+    if off(c) > TOLERANCE || off(d) > TOLERANCE {
+        return None;
+    }
+    let (tc, td) = ((c - a).dot(u), (d - a).dot(u));
+    let lo = tc.min(td).max(0.);
+    let hi = tc.max(td).min(len);
+    if hi - lo > TOLERANCE {
+        Some((a + u * lo, a + u * hi))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tesselate::*;
+
+    const FRAME: Frame = Frame {
+        x: 0,
+        y: 0,
+        w: 200,
+        h: 150,
+        seamless: false,
+    };
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<Pos> {
+        vec![Pos(x0, y0), Pos(x1, y0), Pos(x1, y1), Pos(x0, y1)]
+    }
+
+    fn assert_symmetric(graph: &Adjacency) {
+        for i in 0..graph.len() {
+            for &j in graph.neighbours(i) {
+                assert_ne!(i, j);
+                assert!(graph.neighbours(j).contains(&i), "{} and {}", i, j);
+            }
+        }
+        for e in graph.edges() {
+            assert!(graph.neighbours(e.tiles.0).contains(&e.tiles.1));
+        }
+    }
+
+    #[test]
+    fn corners_in_the_middle_of_sides() {
+        // Two bricks on top of one that straddles them, and one that only touches a corner
+        let graph = Adjacency::new(&[
+            rect(0., 0., 2., 1.),
+            rect(2., 0., 4., 1.),
+            rect(1., 1., 3., 2.),
+            rect(4., 1., 5., 2.),
+        ]);
+        assert_eq!(graph.neighbours(0), &[1, 2]);
+        assert_eq!(graph.neighbours(1), &[0, 2]);
+        assert_eq!(graph.neighbours(2), &[0, 1]);
+        assert!(graph.neighbours(3).is_empty());
+        assert_eq!(graph.edges().len(), 3);
+    }
+
+    #[test]
+    fn neighbours_are_symmetric() {
+        let f = FRAME;
+        let tilers: [&dyn Fn(&mut TileSink); 4] = [
+            &|s| tile_squares(&f, 10., 17, s),
+            &|s| tile_bricks(&f, 10., 17, s),
+            &|s| pentagons_type8(&f, 10., 17, s),
+            &|s| tile_archimedean(&f, Archimedean::SnubHexagonal, 10., 17, s),
+        ];
+        for tiler in tilers {
+            let mut tiles = Vec::new();
+            tiler(&mut |pos, path| tiles.push((pos, path)));
+            let mut graph = Adjacency::from_tiles(&tiles);
+            assert_symmetric(&graph);
+            assert!(graph.edges().len() >= tiles.len());
+            let keep: Vec<bool> = (0..tiles.len()).map(|i| i % 3 != 0).collect();
+            graph.retain(&keep);
+            assert_eq!(graph.len(), keep.iter().filter(|&&k| k).count());
+            assert_symmetric(&graph);
+            // Tiles grouped by pairs
+            let node: Vec<usize> = (0..graph.len()).map(|i| i / 2).collect();
+            graph.merge(&node);
+            assert_eq!(graph.len(), node.len().div_ceil(2));
+            assert_symmetric(&graph);
+        }
+    }
+
+    #[test]
+    fn squares_have_four_neighbours() {
+        let f = FRAME;
+        let mut tiles = Vec::new();
+        tile_squares(&f, 10., 0, &mut |pos, path| tiles.push((pos, path)));
+        let graph = Adjacency::from_tiles(&tiles);
+        for (i, (pos, _)) in tiles.iter().enumerate() {
+            // Those of the margin can be on the rim of the tiling
+            if (0. ..f.w as f64).contains(&pos.0) && (0. ..f.h as f64).contains(&pos.1) {
+                assert_eq!(graph.neighbours(i).len(), 4, "{:?}", pos);
+            }
+        }
+    }
+}
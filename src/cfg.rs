@@ -1,9 +1,13 @@
+use crate::adjacency::Adjacency;
 use crate::paint::*;
 use crate::prelude::*;
+use crate::raster::Backend;
 use crate::scene::*;
 use crate::svg::*;
 use crate::tesselate::*;
-use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::HashMap;
+use std::f64::consts::FRAC_1_PI;
 use std::rc::Rc;
 
 /// General information on a scene
@@ -18,10 +22,39 @@ pub struct SceneCfg {
     pub var_stripes: usize,
     pub size_tiling: f64,
     pub nb_delaunay: usize,
+    /// Tilings declared in the configuration, referred to by `Tiling::Custom`
+    pub custom_tilings: Vec<CustomTiling>,
+    pub lloyd_iterations: usize,
+    /// Placement of the points of Delaunay and Voronoi tilings
+    pub sampling: Sampling,
+    /// How the size of the tiles varies, with `size_tiling` where the variation starts
+    /// and `size_ratio` times that where it ends
+    pub size_variation: SizeVariation,
+    pub size_ratio: f64,
+    /// Truchet tiles turn according to the pattern item they fall in, rather than at random
+    pub truchet_by_pattern: bool,
+    /// Largest displacement of the vertices, relative to the size of the tiles
+    pub jitter: f64,
+    /// Distance over which the displacement varies, relative to the size of the tiles
+    pub jitter_smoothness: f64,
+    /// How the colors of neighbouring tiles relate
+    pub coloring: Coloring,
+    /// Brightness difference between successive kinds of tiles
+    pub kind_shade: isize,
     pub width_pattern: f64,
     pub line_width: f64,
     pub line_color: Color,
+    /// Width of the gap left on each side of a tile
+    pub inset: f64,
+    /// Radius of the rounded corners of tiles
+    pub corner_radius: f64,
+    /// Color showing through the gaps between tiles, background of the scene if absent
+    pub grout_color: Option<Color>,
     pub tightness_spiral: f64,
+    pub themed: bool,
+    pub effects: Effects,
+    pub page_size: Option<(f64, f64)>,
+    pub backend: Backend,
 }
 
 /// A trait to box scene items and make them generic.
@@ -61,6 +94,43 @@ impl SceneCfg {
         }
     }
 
+    /// Color under the tiles, shown when they are inset
+    pub fn grout(&self, scene: &Scene) -> Option<Path> {
+        if self.inset <= 0. {
+            return None;
+        }
+        let color = self.grout_color.unwrap_or_else(|| scene.bg.base());
+        let (lo, hi) = self.frame.bounds();
+        let rect = Data::polygon(vec![lo, Pos(hi.0, lo.1), hi, Pos(lo.0, hi.1)]);
+        Some(
+            Path::new(rect)
+                .with_fill_color(color)
+                .with_stroke_color(color),
+        )
+    }
+
+    /// Shift of the channels of a tile of the given kind, lower kinds than the neutral one
+    /// are made lighter
+    pub fn kind_shift(&self, kind: usize) -> isize {
+        -self.kind_shade * (kind as isize - self.tiling.neutral_kind() as isize)
+    }
+
+    /// Shift of each kind of tile that the tiling produces
+    pub fn kind_shifts(&self) -> Vec<isize> {
+        let kinds = match self.tiling {
+            Tiling::Cubes => 3,
+            Tiling::KitesAndDarts | Tiling::PenroseRhombs | Tiling::Hats | Tiling::Truchet(_) => 2,
+            Tiling::Custom(idx) => self.custom_tilings[idx]
+                .tiles
+                .iter()
+                .filter_map(|(_, kind)| kind.map(|k| k + 1))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        };
+        (0..kinds).map(|k| self.kind_shift(k)).collect()
+    }
+
     /// Match pattern to function that generates it
     pub fn create_items(&self, rng: &mut StdRng) -> Vec<Rc<dyn Contains>> {
         match self.pattern {
@@ -77,28 +147,201 @@ impl SceneCfg {
     }
 
     /// Math tiling to function that generates it
-    pub fn make_tiling(&self, rng: &mut StdRng) -> Vec<(Pos, Path)> {
-        match self.tiling {
-            Tiling::Hexagons => tile_hexagons(&self.frame, self.size_tiling, rng.gen_range(0, 360)),
+    pub fn make_tiling(&self, scene: &Scene, rng: &mut StdRng) -> Vec<(Pos, Path)> {
+        let mut items = Vec::new();
+        self.for_each_tile(scene, rng, |_, pos, path| items.push((pos, path)));
+        items
+    }
+
+    /// Tiles along with the graph of the tiles that share a side.
+    /// The graph is built before tiles are inset, which would leave them no common side.
+    /// On a seamless frame, the copies of a tile on both sides of the frame are one node.
+    pub fn make_tiling_graph(
+        &self,
+        scene: &Scene,
+        rng: &mut StdRng,
+    ) -> (Vec<GraphTile>, Adjacency) {
+        let frame = &self.frame;
+        let mut items = Vec::new();
+        let mut rngs = Vec::new();
+        self.lay_tiles(scene, rng, |tile_rng, pos, path| {
+            items.push((pos, path));
+            rngs.push(frame.seamless.then(|| tile_rng.clone()));
+        });
+        let mut graph = Adjacency::from_tiles(&items);
+        let mut keep = Vec::with_capacity(items.len());
+        let items = items
+            .into_iter()
+            .zip(rngs)
+            .filter_map(|((pos, path), rng)| {
+                let path = self.trim(path);
+                keep.push(path.is_some());
+                path.map(|path| (pos, path, rng))
+            })
+            .collect::<Vec<_>>();
+        graph.retain(&keep);
+        // Copies have the same wrapped position
+        let mut nodes = HashMap::new();
+        let tiles = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pos, path, rng))| {
+                let node = if frame.seamless {
+                    let next = nodes.len();
+                    *nodes.entry(periodic_hash(frame, pos, 0)).or_insert(next)
+                } else {
+                    i
+                };
+                GraphTile {
+                    pos,
+                    path,
+                    node,
+                    rng,
+                }
+            })
+            .collect::<Vec<_>>();
+        if frame.seamless {
+            graph.merge(&tiles.iter().map(|t| t.node).collect::<Vec<_>>());
+        }
+        (tiles, graph)
+    }
+
+    /// Generate the tiling one tile at a time.
+    /// All random choices of the tiling are made before the first tile is handed over,
+    /// so `f` can keep drawing from the same generator.
+    pub fn for_each_tile<F>(&self, scene: &Scene, rng: &mut StdRng, mut f: F)
+    where
+        F: FnMut(&mut StdRng, Pos, Path),
+    {
+        self.lay_tiles(scene, rng, |rng, pos, path| {
+            if let Some(path) = self.trim(path) {
+                f(rng, pos, path)
+            }
+        })
+    }
+
+    /// Leave a gap around the tile and round its corners.
+    /// Tiles that the gap swallows are dropped.
+    fn trim(&self, mut path: Path) -> Option<Path> {
+        if self.inset > 0. {
+            path.data = path.data.inset(self.inset)?;
+        }
+        if self.corner_radius > 0. {
+            path.data = path.data.rounded(self.corner_radius);
+        }
+        Some(path)
+    }
+
+    /// Tiles laid edge to edge: stretched by the size field and jittered, but not trimmed
+    fn lay_tiles<F>(&self, scene: &Scene, rng: &mut StdRng, mut f: F)
+    where
+        F: FnMut(&mut StdRng, Pos, Path),
+    {
+        let frame = &self.frame;
+        let field = self.size_field(rng);
+        // Lattices are built with the smallest tiles, then stretched
+        let size = self.size_tiling * field.as_ref().map_or(1., |field| field.range().0);
+        let tiler: Box<dyn Fn(&mut TileSink)> = match self.tiling {
+            Tiling::Hexagons => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_hexagons(frame, size, rot, sink))
+            }
             Tiling::Triangles => {
-                tile_triangles(&self.frame, self.size_tiling, rng.gen_range(0, 360))
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_triangles(frame, size, rot, sink))
             }
             Tiling::HexagonsAndTriangles => {
-                tile_hybrid_hexagons_triangles(&self.frame, self.size_tiling, rng.gen_range(0, 360))
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_hybrid_hexagons_triangles(frame, size, rot, sink))
             }
             Tiling::SquaresAndTriangles => {
-                tile_hybrid_squares_triangles(&self.frame, self.size_tiling, rng.gen_range(0, 360))
-            }
-            Tiling::Rhombus => tile_rhombus(
-                &self.frame,
-                self.size_tiling,
-                (rng.gen::<f64>() * 0.6 + 0.4) * self.size_tiling,
-                rng.gen_range(0, 360),
-            ),
-            Tiling::Delaunay => random_delaunay(&self.frame, rng, self.nb_delaunay),
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_hybrid_squares_triangles(frame, size, rot, sink))
+            }
+            Tiling::Rhombus => {
+                let sdiag = (rng.gen::<f64>() * 0.6 + 0.4) * size;
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_rhombus(frame, size, sdiag, rot, sink))
+            }
+            Tiling::Custom(idx) => {
+                let rot = rng.gen_range(0, 360);
+                let custom = &self.custom_tilings[idx];
+                Box::new(move |sink| tile_custom(frame, custom, size, rot, sink))
+            }
+            Tiling::Cubes => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_cubes(frame, size, rot, sink))
+            }
+            Tiling::Squares => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_squares(frame, size, rot, sink))
+            }
+            Tiling::Bricks => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_bricks(frame, size, rot, sink))
+            }
+            Tiling::Herringbone => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_herringbone(frame, size, rot, sink))
+            }
+            Tiling::Basketweave => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_basketweave(frame, size, rot, sink))
+            }
+            Tiling::KitesAndDarts => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| penrose(frame, size, true, rot, sink))
+            }
+            Tiling::PenroseRhombs => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| penrose(frame, size, false, rot, sink))
+            }
+            Tiling::Hats => {
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_hats(frame, size, rot, sink))
+            }
+            Tiling::Archimedean(kind) => {
+                let kind = kind.unwrap_or_else(|| Archimedean::choose(rng));
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_archimedean(frame, kind, size, rot, sink))
+            }
+            Tiling::Laves(kind) => {
+                let kind = kind.unwrap_or_else(|| Archimedean::choose(rng));
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| tile_laves(frame, kind, size, rot, sink))
+            }
+            Tiling::Adaptive => {
+                let triangles = rng.gen::<bool>();
+                let rot = rng.gen_range(0, 360);
+                let item_at = move |p| scene.item_at(p);
+                Box::new(move |sink| tile_adaptive(frame, size, rot, triangles, &item_at, sink))
+            }
+            Tiling::Truchet(style) => {
+                let style = style.unwrap_or_else(|| Truchet::choose(rng));
+                let rot = rng.gen_range(0, 360);
+                let seed = rng.gen::<u64>();
+                let by_pattern = self.truchet_by_pattern;
+                let orient = move |p: Pos| {
+                    if by_pattern {
+                        scene.item_at(p)
+                    } else {
+                        periodic_hash(frame, p, seed) as usize
+                    }
+                };
+                Box::new(move |sink| tile_truchet(frame, style, size, rot, &orient, sink))
+            }
+            Tiling::Delaunay => {
+                let pts = self.sample_points(scene, field.as_ref(), rng);
+                Box::new(move |sink| delaunay(frame, &pts, sink))
+            }
+            Tiling::Voronoi => {
+                let pts = self.sample_points(scene, field.as_ref(), rng);
+                let relax = self.lloyd_iterations;
+                Box::new(move |sink| voronoi(frame, &pts, relax, sink))
+            }
             Tiling::Pentagons(n) => {
                 let n = match n {
-                    0 => rng.gen_range(1, 7),
+                    0 => rng.gen_range(1, 16),
                     n => n,
                 };
                 let ptiler = match n {
@@ -108,12 +351,197 @@ impl SceneCfg {
                     4 => pentagons_type4,
                     5 => pentagons_type5,
                     6 => pentagons_type6,
+                    7 => pentagons_type7,
+                    8 => pentagons_type8,
+                    9 => pentagons_type9,
+                    10 => pentagons_type10,
+                    11 => pentagons_type11,
+                    12 => pentagons_type12,
+                    13 => pentagons_type13,
+                    14 => pentagons_type14,
+                    15 => pentagons_type15,
                     _ => unreachable!(),
                 };
-                ptiler(&self.frame, self.size_tiling, rng.gen_range(0, 360))
+                let rot = rng.gen_range(0, 360);
+                Box::new(move |sink| ptiler(frame, size, rot, sink))
+            }
+        };
+        let jitter = if self.jitter > 0. {
+            // Delaunay and Voronoi have no tile size, use the mean spacing of their points
+            let unit = if self.size_tiling > 0. {
+                self.size_tiling
+            } else {
+                ((frame.w * frame.h) as f64 / self.nb_delaunay.max(1) as f64).sqrt()
+            };
+            let wavelength = self.jitter_smoothness.max(0.1) * unit;
+            let jitter = Jitter::new(rng.gen(), self.jitter * unit, wavelength);
+            Some(if frame.seamless {
+                jitter.with_period(frame)
+            } else {
+                jitter
+            })
+        } else {
+            None
+        };
+        // Copies of a tile on both sides of a seamless frame must get the same colors,
+        // so each tile draws from its own generator seeded by its wrapped position
+        let tile_seed = if frame.seamless {
+            Some(rng.gen::<u64>())
+        } else {
+            None
+        };
+        // Delaunay and Voronoi follow the field through the density of their points
+        let stretch = match (&field, self.tiling) {
+            (_, Tiling::Delaunay | Tiling::Voronoi) | (None, _) => None,
+            (Some(field), _) => Some(Stretch::new(frame, field, size)),
+        };
+        let (lo, hi) = frame.bounds();
+        tiler(&mut |mut pos, mut path| {
+            if let Some(stretch) = &stretch {
+                path.data = stretch.distort(&path.data);
+                pos = stretch.apply(pos);
+                // Stretching pushes many tiles out of the frame
+                let pts = path.data.vertices();
+                if pts.iter().all(|p| p.0 < lo.0)
+                    || pts.iter().all(|p| p.0 > hi.0)
+                    || pts.iter().all(|p| p.1 < lo.1)
+                    || pts.iter().all(|p| p.1 > hi.1)
+                {
+                    return;
+                }
+            }
+            if let Some(jitter) = &jitter {
+                path.data = jitter.distort(&path.data);
+                pos = jitter.apply(pos);
+            }
+            match tile_seed {
+                Some(seed) => {
+                    let mut tile_rng = StdRng::seed_from_u64(periodic_hash(frame, pos, seed));
+                    f(&mut tile_rng, pos, path)
+                }
+                None => f(rng, pos, path),
             }
+        });
+    }
+
+    /// Points of a Delaunay or Voronoi tiling, spread according to `sampling`
+    /// and further apart where the size field calls for larger tiles
+    fn sample_points(
+        &self,
+        scene: &Scene,
+        field: Option<&SizeField>,
+        rng: &mut StdRng,
+    ) -> Vec<Pos> {
+        let frame = &self.frame;
+        let n = self.nb_delaunay;
+        let (min, max) = field.map_or((1., 1.), |field| field.range());
+        let spread = |p: Pos| field.map_or(1., |field| field.scale(p));
+        match self.sampling {
+            Sampling::Uniform => random_points(frame, rng, n, &|p| spread(p) / min),
+            Sampling::Poisson(spacing, density) => match density {
+                Density::Uniform => poisson_points(frame, rng, n, spacing, &|p| spread(p) / max),
+                Density::Center => {
+                    let center = frame.center();
+                    let reach = center.dist(Pos(frame.x as f64, frame.y as f64));
+                    let scale =
+                        |p: Pos| (0.35 + 0.65 * (p.dist(center) / reach).min(1.)) * spread(p) / max;
+                    poisson_points(frame, rng, n, spacing, &scale)
+                }
+                Density::Boundaries => {
+                    // Look around at the typical spacing for a change of scene item
+                    let step = spacing
+                        .unwrap_or_else(|| ((frame.w * frame.h) as f64 / n.max(1) as f64).sqrt());
+                    let near_edge = |p: Pos, r: f64| {
+                        let item = scene.item_at(p);
+                        (0..8).any(|k| scene.item_at(p + Pos::polar(k * 45, r)) != item)
+                    };
+                    let scale = |p: Pos| {
+                        let s = if near_edge(p, step) {
+                            0.35
+                        } else if near_edge(p, 2.5 * step) {
+                            0.6
+                        } else {
+                            1.
+                        };
+                        s * spread(p) / max
+                    };
+                    poisson_points(frame, rng, n, spacing, &scale)
+                }
+            },
         }
     }
+
+    /// Draw the random parts of the size field: focal point, direction or noise
+    fn size_field(&self, rng: &mut StdRng) -> Option<SizeField> {
+        let frame = &self.frame;
+        let (w, h) = (frame.w as f64, frame.h as f64);
+        let corners = [
+            Pos(frame.x as f64, frame.y as f64),
+            Pos(frame.x as f64 + w, frame.y as f64),
+            Pos(frame.x as f64, frame.y as f64 + h),
+            Pos(frame.x as f64 + w, frame.y as f64 + h),
+        ];
+        let ratio = self.size_ratio;
+        match self.size_variation {
+            SizeVariation::Uniform => None,
+            SizeVariation::Radial => {
+                // Somewhere in the middle half of the frame
+                let focus = Pos(
+                    frame.x as f64 + w * rng.gen_range(0.25, 0.75),
+                    frame.y as f64 + h * rng.gen_range(0.25, 0.75),
+                );
+                let reach = corners.iter().map(|c| c.dist(focus)).fold(0., f64::max);
+                Some(SizeField::Radial {
+                    focus,
+                    reach,
+                    ratio,
+                })
+            }
+            SizeVariation::Ramp => {
+                let dir = Pos::polar(rng.gen_range(0, 360), 1.);
+                let proj = |c: &Pos| c.dot(dir);
+                let origin = *corners
+                    .iter()
+                    .min_by(|a, b| proj(a).total_cmp(&proj(b)))
+                    .unwrap();
+                let length = corners
+                    .iter()
+                    .map(|c| proj(c) - proj(&origin))
+                    .fold(0., f64::max);
+                Some(SizeField::Ramp {
+                    origin,
+                    dir,
+                    length,
+                    ratio,
+                })
+            }
+            SizeVariation::Noise => Some(SizeField::Noise {
+                noise: Jitter::new(rng.gen(), 1., w.max(h) / 2.5),
+                ratio,
+            }),
+        }
+    }
+}
+
+/// Tile of a neighbour-aware coloring
+pub struct GraphTile {
+    pub pos: Pos,
+    pub path: Path,
+    /// Node of the tile in the graph, numbered in order of first appearance
+    pub node: usize,
+    /// Generator of the tile on a seamless frame, so that its copies are painted alike.
+    /// Other tiles are painted with the main generator.
+    pub rng: Option<StdRng>,
+}
+
+/// Hash of a position that is the same for all copies of a point on a seamless frame.
+/// Copies only differ by rounding errors once wrapped. Lattice points often fall on
+/// half pixels, so they are snapped to whole pixels shifted by an irrational fraction,
+/// then wrapped again in case they moved onto the far edge.
+fn periodic_hash(frame: &Frame, p: Pos, seed: u64) -> u64 {
+    let p = frame.wrap(p);
+    let snap = |x: f64| (x + FRAC_1_PI).floor();
+    frame.wrap(Pos(snap(p.0), snap(p.1))).seeded_hash(seed)
 }
 
 /// Available patterns, open to additions
@@ -134,7 +562,7 @@ impl Pattern {
     /// Pick a random pattern (fallback if no other pattern choosing method is specified)
     pub fn choose(rng: &mut StdRng) -> Self {
         use Pattern::*;
-        *vec![
+        *[
             FreeCircles,
             FreeTriangles,
             FreeStripes,
@@ -148,6 +576,66 @@ impl Pattern {
         .choose(rng)
         .unwrap()
     }
+
+    /// Whether the items stay within a bounded region, so that their copies around
+    /// a seamless frame leave room for the background. Stripes, waves and spirals
+    /// reach across the frame, and their copies would cover it almost entirely.
+    pub fn is_bounded(self) -> bool {
+        matches!(
+            self,
+            Pattern::FreeCircles | Pattern::FreeTriangles | Pattern::ConcentricCircles
+        )
+    }
+}
+
+/// How the points of Delaunay and Voronoi tilings are placed
+#[derive(Debug, Clone, Copy)]
+pub enum Sampling {
+    /// Independent uniform draws
+    Uniform,
+    /// Poisson-disk sampling, with an optional minimum distance between points
+    /// (derived from the number of points if absent)
+    Poisson(Option<f64>, Density),
+}
+
+/// How the colors of neighbouring tiles relate
+#[derive(Debug, Clone, Copy)]
+pub enum Coloring {
+    /// Each tile is colored on its own
+    Independent,
+    /// Neighbours stand out from each other
+    Contrast,
+    /// Tiles of a scene item take one of a few shades, unlike their neighbours
+    Palette,
+    /// Colors vary gradually from a tile to the next
+    Smooth,
+    /// Tiles alive after some generations of a cellular automaton take a theme color
+    Life(usize),
+    /// Tiles take a theme color by the concentration of a chemical
+    /// after some steps of a Gray–Scott reaction–diffusion
+    ReactionDiffusion(usize),
+}
+
+/// How the size of the tiles varies across the frame
+#[derive(Debug, Clone, Copy)]
+pub enum SizeVariation {
+    Uniform,
+    /// With the distance to a focal point
+    Radial,
+    /// Along a direction
+    Ramp,
+    /// Following a smooth noise
+    Noise,
+}
+
+/// Where Poisson-disk sampling packs points more tightly
+#[derive(Debug, Clone, Copy)]
+pub enum Density {
+    Uniform,
+    /// Towards the middle of the frame
+    Center,
+    /// Along the edges of the pattern items
+    Boundaries,
 }
 
 ///Available tilings, open to additions
@@ -160,13 +648,32 @@ pub enum Tiling {
     Rhombus,
     Delaunay,
     Pentagons(u8),
+    Squares,
+    Bricks,
+    Herringbone,
+    Basketweave,
+    Voronoi,
+    KitesAndDarts,
+    PenroseRhombs,
+    Hats,
+    /// One of the Archimedean tilings, or a random one
+    Archimedean(Option<Archimedean>),
+    /// Dual of one of the Archimedean tilings, or of a random one
+    Laves(Option<Archimedean>),
+    /// Triangles or squares that get smaller along the edges of the pattern
+    Adaptive,
+    /// One of the Truchet motifs, or a random one
+    Truchet(Option<Truchet>),
+    Cubes,
+    /// Index of a tiling declared in the configuration
+    Custom(usize),
 }
 
 impl Tiling {
     /// Pick a random tiling (fallback if no other tiling choosing method is specified)
     pub fn choose(rng: &mut StdRng) -> Self {
         use Tiling::*;
-        *vec![
+        *[
             Hexagons,
             Triangles,
             HexagonsAndTriangles,
@@ -174,8 +681,109 @@ impl Tiling {
             Rhombus,
             Delaunay,
             Pentagons(0),
+            Squares,
+            Bricks,
+            Herringbone,
+            Basketweave,
+            Voronoi,
+            KitesAndDarts,
+            PenroseRhombs,
+            Hats,
+            Archimedean(None),
+            Laves(None),
+            Adaptive,
+            Truchet(None),
+            Cubes,
         ]
         .choose(rng)
         .unwrap()
     }
+
+    /// Kind of tile that keeps the color of the scene, lower kinds are made lighter
+    pub fn neutral_kind(self) -> usize {
+        match self {
+            Tiling::Cubes => 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether the tiling repeats, so that it can wrap around a seamless frame
+    pub fn is_periodic(self) -> bool {
+        !matches!(
+            self,
+            Tiling::KitesAndDarts | Tiling::PenroseRhombs | Tiling::Hats
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserializer::MetaConfig;
+
+    /// Points of a Voronoi tiling sampled with the given Poisson density
+    fn poisson_samples(density: &str, id: u64) -> (Scene, Vec<Pos>) {
+        let toml = format!(
+            "[global]\nwidth = 240\nheight = 180\n\
+             [data.tilings]\nsampling = \"poisson\"\npoisson_distance = 8\npoisson_density = \"{}\"\n",
+            density
+        );
+        let mut rng = StdRng::seed_from_u64(id);
+        let cfg = MetaConfig::from_string(toml).pick_cfg(&mut rng, id);
+        let scene = Scene::new(&cfg, &mut rng);
+        let pts = cfg.sample_points(&scene, None, &mut rng);
+        (scene, pts)
+    }
+
+    /// Average distance to the nearest other point, over the points that match `filter`
+    fn mean_spacing(pts: &[Pos], filter: impl Fn(Pos) -> bool) -> f64 {
+        let near: Vec<_> = pts
+            .iter()
+            .filter(|p| filter(**p))
+            .map(|&p| {
+                pts.iter()
+                    .map(|q| q.dist(p))
+                    .filter(|d| *d > 0.)
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        near.iter().sum::<f64>() / near.len() as f64
+    }
+
+    #[test]
+    fn poisson_densities_gather_points_where_asked() {
+        for id in 0..3 {
+            let (_, pts) = poisson_samples("center", id);
+            let center = Pos(120., 90.);
+            let inner = mean_spacing(&pts, |p| p.dist(center) < 40.);
+            let outer = mean_spacing(&pts, |p| p.dist(center) > 110.);
+            assert!(
+                inner < outer * 0.7,
+                "center, id {}: {} vs {}",
+                id,
+                inner,
+                outer
+            );
+
+            let (scene, pts) = poisson_samples("boundaries", id);
+            let near_edge = |p: Pos| {
+                let item = scene.item_at(p);
+                (0..8).any(|k| scene.item_at(p + Pos::polar(k * 45, 8.)) != item)
+            };
+            let edges = mean_spacing(&pts, near_edge);
+            let inside = mean_spacing(&pts, |p| !near_edge(p));
+            assert!(
+                edges < inside * 0.7,
+                "boundaries, id {}: {} vs {}",
+                id,
+                edges,
+                inside
+            );
+
+            // Same id, same points
+            let (_, again) = poisson_samples("boundaries", id);
+            assert_eq!(pts.len(), again.len());
+            assert!(pts.iter().zip(&again).all(|(p, q)| p.dist(*q) == 0.));
+        }
+    }
 }
@@ -2,9 +2,17 @@ use rand::{rngs::StdRng, Rng};
 use std::convert::TryInto;
 use std::fmt;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color(pub usize, pub usize, pub usize);
 
+/// A color expressed as an entry of the scene palette plus a small per-channel offset.
+/// The offset is `None` when the color does not derive from the palette (salt).
+#[derive(Clone, Copy, Debug)]
+pub struct Tint {
+    pub index: usize,
+    pub offset: Option<[isize; 3]>,
+}
+
 impl Color {
     /// Ensure that all RGB values are within [[1; 100]]
     fn validate(mut self) -> Self {
@@ -39,6 +47,27 @@ impl Color {
         self
     }
 
+    /// Same change of brightness on all channels
+    pub fn shift(self, amount: isize) -> Self {
+        let f = |c: usize| (c as isize + amount).max(0) as usize;
+        Self(f(self.0), f(self.1), f(self.2)).validate()
+    }
+
+    /// Channels clamped to a byte each
+    pub fn to_rgb(self) -> [u8; 3] {
+        let c = self.validate();
+        [c.0 as u8, c.1 as u8, c.2 as u8]
+    }
+
+    /// Per-channel difference with a reference color
+    pub fn offset_from(self, base: Self) -> [isize; 3] {
+        [
+            self.0 as isize - base.0 as isize,
+            self.1 as isize - base.1 as isize,
+            self.2 as isize - base.2 as isize,
+        ]
+    }
+
     /// Generate color
     pub fn random(rng: &mut StdRng) -> Self {
         Self(
@@ -0,0 +1,444 @@
+use crate::adjacency::Adjacency;
+use crate::cfg::Coloring;
+use crate::prelude::*;
+use crate::scene::ColorItem;
+use crate::svg::Path;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use std::collections::BinaryHeap;
+
+/// Smallest difference on some channel between the colors of neighbours
+const CONTRAST: usize = 16;
+/// Brightness shifts of the shades that neighbours pick from
+const PALETTE: [isize; 5] = [-40, -20, 0, 20, 40];
+/// Rounds of averaging with the neighbours
+const SMOOTH_STEPS: usize = 3;
+/// Share of the tiles alive at the start of the cellular automaton
+const LIFE_DENSITY: f64 = 0.4;
+/// Share of alive neighbours for a tile to come to life, and to stay alive.
+/// Game of Life counts (3 and 2 to 3 out of 8) as fractions, so that they fit any tiling.
+const LIFE_BIRTH: (f64, f64) = (0.3, 0.5);
+const LIFE_SURVIVAL: (f64, f64) = (0.2, 0.5);
+/// Feed and kill rates of the Gray–Scott model, one pair is picked per image
+const GRAY_SCOTT: [(f64, f64); 4] = [
+    (0.055, 0.062),
+    (0.0367, 0.0649),
+    (0.029, 0.057),
+    (0.039, 0.058),
+];
+/// Diffusion of the two chemicals, relative to the mean of the neighbours
+const DIFFUSION: (f64, f64) = (1., 0.5);
+/// Share of the tiles where the second chemical is poured at the start
+const GRAY_SCOTT_SEEDS: f64 = 0.005;
+/// Reach of each drop, in steps from a tile to its neighbours
+const GRAY_SCOTT_DROP: usize = 3;
+
+/// Adjust the colors of painted tiles according to their neighbours.
+/// `base` is the color a tile has before any random variation,
+/// `theme` draws a color from the theme for the states of the automata.
+/// Tiles painted with salt keep their color, except to stand out from their neighbours.
+pub fn recolor(
+    coloring: Coloring,
+    tiles: &mut [Path],
+    graph: &Adjacency,
+    base: &dyn Fn(&Path) -> Color,
+    theme: &dyn Fn(&mut StdRng) -> ColorItem,
+    rng: &mut StdRng,
+) {
+    match coloring {
+        Coloring::Independent => (),
+        Coloring::Contrast => contrast(tiles, graph),
+        Coloring::Palette => palette(tiles, graph, base, rng),
+        Coloring::Smooth => smooth(tiles, graph),
+        Coloring::Life(steps) => {
+            let alive = life(graph, steps, rng);
+            let levels = alive
+                .iter()
+                .map(|&a| if a { 1. } else { 0. })
+                .collect::<Vec<_>>();
+            blend(tiles, &levels, &theme(rng), rng)
+        }
+        Coloring::ReactionDiffusion(steps) => {
+            let levels = gray_scott(graph, steps, rng);
+            blend(tiles, &levels, &theme(rng), rng)
+        }
+    }
+}
+
+/// Move the color of each tile towards a color of `item` by its level, between 0 and 1
+fn blend(tiles: &mut [Path], levels: &[f64], item: &ColorItem, rng: &mut StdRng) {
+    for (path, &t) in tiles.iter_mut().zip(levels) {
+        let c = item.sample(rng);
+        if t > 0. {
+            let c = c.meanpoint(path.fill_color, (t.clamp(0., 1.) * 100.).round() as usize);
+            set_fill(path, c);
+        }
+    }
+}
+
+/// Seeded cellular automaton on the tiles: which tiles are alive after `steps` generations
+fn life(graph: &Adjacency, steps: usize, rng: &mut StdRng) -> Vec<bool> {
+    let mut alive = (0..graph.len())
+        .map(|_| rng.gen::<f64>() < LIFE_DENSITY)
+        .collect::<Vec<_>>();
+    let within = |f: f64, (lo, hi): (f64, f64)| lo <= f && f <= hi;
+    for _ in 0..steps {
+        alive = (0..graph.len())
+            .map(|i| {
+                let near = graph.neighbours(i);
+                let count = near.iter().filter(|&&j| alive[j]).count();
+                let f = count as f64 / near.len().max(1) as f64;
+                if alive[i] {
+                    within(f, LIFE_SURVIVAL)
+                } else {
+                    within(f, LIFE_BIRTH)
+                }
+            })
+            .collect();
+    }
+    alive
+}
+
+/// Gray–Scott reaction–diffusion on the tiles, the diffusion following the shared sides.
+/// Returns the concentration of the second chemical after `steps` steps, scaled to at most 1.
+fn gray_scott(graph: &Adjacency, steps: usize, rng: &mut StdRng) -> Vec<f64> {
+    let n = graph.len();
+    let (feed, kill) = *GRAY_SCOTT.choose(rng).unwrap();
+    let (mut u, mut v) = (vec![1.; n], vec![0.; n]);
+    for i in 0..n {
+        if rng.gen::<f64>() < GRAY_SCOTT_SEEDS {
+            // A drop reaching a few tiles around, a single tile would wash away
+            let mut drop = vec![i];
+            for _ in 0..GRAY_SCOTT_DROP {
+                let ring = drop
+                    .iter()
+                    .flat_map(|&j| graph.neighbours(j).iter().copied())
+                    .collect::<Vec<_>>();
+                drop.extend(ring);
+                drop.sort_unstable();
+                drop.dedup();
+            }
+            for j in drop {
+                u[j] = 0.5;
+                v[j] = 1.;
+            }
+        }
+    }
+    let laplacian = |x: &[f64], i: usize| {
+        let near = graph.neighbours(i);
+        if near.is_empty() {
+            return 0.;
+        }
+        near.iter().map(|&j| x[j]).sum::<f64>() / near.len() as f64 - x[i]
+    };
+    for _ in 0..steps {
+        let (du, dv): (Vec<f64>, Vec<f64>) = (0..n)
+            .map(|i| {
+                let uvv = u[i] * v[i] * v[i];
+                (
+                    DIFFUSION.0 * laplacian(&u, i) - uvv + feed * (1. - u[i]),
+                    DIFFUSION.1 * laplacian(&v, i) + uvv - (feed + kill) * v[i],
+                )
+            })
+            .unzip();
+        for i in 0..n {
+            u[i] = (u[i] + du[i]).clamp(0., 1.);
+            v[i] = (v[i] + dv[i]).clamp(0., 1.);
+        }
+    }
+    let max = v.iter().cloned().fold(0., f64::max);
+    if max > 0. {
+        v.iter_mut().for_each(|x| *x /= max);
+    }
+    v
+}
+
+/// Scene item the color of a tile comes from, `None` for salt
+fn item(path: &Path) -> Option<usize> {
+    match path.tint {
+        Some(Tint {
+            index,
+            offset: Some(_),
+        }) => Some(index),
+        _ => None,
+    }
+}
+
+/// Change the fill of a tile, its tint and a stroke that matches the fill follow
+fn set_fill(path: &mut Path, c: Color) {
+    let [dr, dg, db] = c.offset_from(path.fill_color);
+    if let Some(tint) = &mut path.tint {
+        tint.offset = tint.offset.map(|[r, g, b]| [r + dr, g + dg, b + db]);
+    }
+    if path.stroke_color == path.fill_color {
+        path.stroke_color = c;
+    }
+    path.fill_color = c;
+}
+
+fn differ(a: Color, b: Color) -> bool {
+    let d = |x: usize, y: usize| x.abs_diff(y);
+    d(a.0, b.0).max(d(a.1, b.1)).max(d(a.2, b.2)) >= CONTRAST
+}
+
+/// Each tile in turn is made lighter or darker until it stands out from
+/// the neighbours that come before it
+fn contrast(tiles: &mut [Path], graph: &Adjacency) {
+    for i in 0..tiles.len() {
+        let c = tiles[i].fill_color;
+        let earlier = graph.neighbours(i).iter().filter(|&&j| j < i);
+        let fits = |c: Color| earlier.clone().all(|&j| differ(c, tiles[j].fill_color));
+        if fits(c) {
+            continue;
+        }
+        let shifts = (1..=4).flat_map(|k| [k, -k]).map(|k| k * CONTRAST as isize);
+        if let Some(c) = shifts.map(|s| c.shift(s)).find(|&c| fits(c)) {
+            set_fill(&mut tiles[i], c);
+        }
+    }
+}
+
+/// Graph coloring of the tiles of each scene item with the shades of `PALETTE`.
+/// Tiles are taken most constrained first (DSATUR), each picks at random among
+/// the shades its neighbours left, or the least used one if there is none.
+fn palette(tiles: &mut [Path], graph: &Adjacency, base: &dyn Fn(&Path) -> Color, rng: &mut StdRng) {
+    let n = tiles.len();
+    let same = |i: usize, j: usize| item(&tiles[i]).is_some() && item(&tiles[i]) == item(&tiles[j]);
+    let mut shade: Vec<Option<usize>> = vec![None; n];
+    // Shades taken by the neighbours of each tile, as a bit set
+    let mut taken = vec![0u32; n];
+    let mut heap = (0..n)
+        .filter(|&i| item(&tiles[i]).is_some())
+        .map(|i| (0, graph.neighbours(i).len(), std::cmp::Reverse(i)))
+        .collect::<BinaryHeap<_>>();
+    while let Some((sat, _, std::cmp::Reverse(i))) = heap.pop() {
+        if shade[i].is_some() || sat != taken[i].count_ones() {
+            continue;
+        }
+        let free = (0..PALETTE.len())
+            .filter(|k| taken[i] & (1 << k) == 0)
+            .collect::<Vec<_>>();
+        let k = match free.choose(rng) {
+            Some(&k) => k,
+            None => {
+                let uses = |k: usize| {
+                    graph
+                        .neighbours(i)
+                        .iter()
+                        .filter(|&&j| shade[j] == Some(k) && same(i, j))
+                        .count()
+                };
+                (0..PALETTE.len()).min_by_key(|&k| uses(k)).unwrap()
+            }
+        };
+        shade[i] = Some(k);
+        for &j in graph.neighbours(i) {
+            if shade[j].is_none() && same(i, j) && taken[j] & (1 << k) == 0 {
+                taken[j] |= 1 << k;
+                heap.push((
+                    taken[j].count_ones(),
+                    graph.neighbours(j).len(),
+                    std::cmp::Reverse(j),
+                ));
+            }
+        }
+    }
+    for (path, k) in tiles.iter_mut().zip(shade) {
+        if let Some(k) = k {
+            let c = base(path).shift(PALETTE[k]);
+            set_fill(path, c);
+        }
+    }
+}
+
+/// Colors are repeatedly replaced by their mean with the neighbours from the same scene item
+fn smooth(tiles: &mut [Path], graph: &Adjacency) {
+    for _ in 0..SMOOTH_STEPS {
+        let colors = tiles
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                item(path)?;
+                let near = graph
+                    .neighbours(i)
+                    .iter()
+                    .filter(|&&j| item(&tiles[j]) == item(path))
+                    .map(|&j| tiles[j].fill_color)
+                    .chain(Some(path.fill_color))
+                    .collect::<Vec<_>>();
+                let mean = |ch: fn(&Color) -> usize| {
+                    (near.iter().map(ch).sum::<usize>() + near.len() / 2) / near.len()
+                };
+                Some(Color(mean(|c| c.0), mean(|c| c.1), mean(|c| c.2)))
+            })
+            .collect::<Vec<_>>();
+        for (path, c) in tiles.iter_mut().zip(colors) {
+            if let Some(c) = c {
+                set_fill(path, c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserializer::MetaConfig;
+    use crate::gen_image::paint_tiles;
+    use crate::scene::Scene;
+    use crate::tesselate::*;
+    use rand::SeedableRng;
+
+    const FRAME: Frame = Frame {
+        x: 0,
+        y: 0,
+        w: 200,
+        h: 150,
+        seamless: false,
+    };
+
+    /// Base color of the scene items of the tiles
+    fn base_of(index: usize) -> Color {
+        [
+            Color(90, 150, 110),
+            Color(120, 120, 120),
+            Color(160, 100, 130),
+        ][index]
+    }
+
+    /// Tiles of the left and right halves of the frame come from two scene items,
+    /// every seventh one is salt
+    fn painted(tiler: &dyn Fn(&mut TileSink)) -> (Vec<Path>, Adjacency) {
+        let mut tiles = Vec::new();
+        tiler(&mut |pos, path| tiles.push((pos, path)));
+        let graph = Adjacency::from_tiles(&tiles);
+        let tiles = tiles
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pos, path))| {
+                let index = if pos.0 < 100. { 1 } else { 2 };
+                let offset = if i % 7 == 0 { None } else { Some([0; 3]) };
+                path.with_fill_color(base_of(index))
+                    .with_tint(Some(Tint { index, offset }))
+            })
+            .collect();
+        (tiles, graph)
+    }
+
+    #[test]
+    fn palette_neighbours_differ() {
+        let f = FRAME;
+        let tilers: [&dyn Fn(&mut TileSink); 4] = [
+            &|s| tile_squares(&f, 8., 17, s),
+            &|s| tile_hexagons(&f, 8., 17, s),
+            &|s| tile_triangles(&f, 8., 17, s),
+            &|s| pentagons_type8(&f, 8., 17, s),
+        ];
+        let base = |path: &Path| base_of(path.tint.unwrap().index);
+        let theme = |_: &mut StdRng| -> ColorItem { unreachable!() };
+        for tiler in tilers {
+            let (mut tiles, graph) = painted(tiler);
+            let mut rng = StdRng::seed_from_u64(0);
+            recolor(
+                Coloring::Palette,
+                &mut tiles,
+                &graph,
+                &base,
+                &theme,
+                &mut rng,
+            );
+            for i in 0..tiles.len() {
+                if item(&tiles[i]).is_none() {
+                    assert_eq!(tiles[i].fill_color, base(&tiles[i]));
+                    continue;
+                }
+                for &j in graph.neighbours(i) {
+                    if item(&tiles[i]) == item(&tiles[j]) {
+                        assert_ne!(tiles[i].fill_color, tiles[j].fill_color, "{} {}", i, j);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn automata_are_deterministic() {
+        let f = FRAME;
+        let hexagons = |s: &mut TileSink| tile_hexagons(&f, 8., 17, s);
+        let (_, graph) = painted(&hexagons);
+        let seeded = StdRng::seed_from_u64;
+        for steps in [0, 1, 12] {
+            let alive = life(&graph, steps, &mut seeded(3));
+            assert_eq!(alive, life(&graph, steps, &mut seeded(3)));
+            assert_ne!(alive, life(&graph, steps, &mut seeded(4)));
+        }
+        for steps in [1, 100, 1000] {
+            let levels = gray_scott(&graph, steps, &mut seeded(3));
+            assert_eq!(levels, gray_scott(&graph, steps, &mut seeded(3)));
+            assert!(levels.iter().all(|v| (0. ..=1.).contains(v)));
+        }
+        // The whole recoloring as well, drawing from the theme included
+        let base = |path: &Path| base_of(path.tint.unwrap().index);
+        let theme = |rng: &mut StdRng| ColorItem {
+            shade: Color(200, 60, 60),
+            deviation: 10,
+            theme: Color(60, 60, 200),
+            distance: rng.gen_range(0, 50),
+            salt: Salt::none(),
+        };
+        for coloring in [Coloring::Life(12), Coloring::ReactionDiffusion(500)] {
+            let run = |seed: u64| {
+                let (mut tiles, graph) = painted(&hexagons);
+                recolor(
+                    coloring,
+                    &mut tiles,
+                    &graph,
+                    &base,
+                    &theme,
+                    &mut seeded(seed),
+                );
+                tiles.iter().map(|p| p.fill_color).collect::<Vec<_>>()
+            };
+            assert_eq!(run(5), run(5));
+        }
+        // On a seamless image, the copies of a tile past the edges are the same node as the tile:
+        // hexagons have no rim, and copies end up in the same state
+        for coloring in ["life", "reaction-diffusion"] {
+            let toml = format!(
+                "[global]\nwidth = 200\nheight = 150\nsize = 12\nseamless = true\n\
+                 coloring = \"{}\"\n\
+                 [shapes]\nonly = [\"H\", \"FC\"]\n[[entry]]\nshapes = [\"only\"]\n",
+                coloring
+            );
+            let setup = || {
+                let mut rng = seeded(7);
+                let cfg = MetaConfig::from_string(toml.clone()).pick_cfg(&mut rng, 7);
+                let scene = Scene::new(&cfg, &mut rng);
+                (rng, cfg, scene)
+            };
+            let (mut rng, cfg, scene) = setup();
+            let (_, graph) = cfg.make_tiling_graph(&scene, &mut rng);
+            assert!((0..graph.len()).all(|i| graph.neighbours(i).len() == 6));
+            let (mut rng, cfg, scene) = setup();
+            let frame = cfg.frame;
+            let mut tiles = Vec::new();
+            paint_tiles(&cfg, &scene, &mut rng, |path| {
+                let pts = path.data.flatten();
+                let mid = pts.iter().fold(Pos::zero(), |acc, p| acc + *p) * (1. / pts.len() as f64);
+                tiles.push((mid, path.fill_color));
+            });
+            let inside = |p: Pos| frame.wrap(p).dist(p) < 1e-6;
+            let mut copies = 0;
+            for &(p, color) in tiles.iter().filter(|(p, _)| !inside(*p)) {
+                let wrapped = frame.wrap(p);
+                if let Some((_, c)) = tiles
+                    .iter()
+                    .find(|(q, _)| inside(*q) && q.dist(wrapped) < 0.01)
+                {
+                    assert_eq!(*c, color, "{} at {:?}", coloring, p);
+                    copies += 1;
+                }
+            }
+            assert!(copies > 0);
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use crate::cfg::SceneCfg;
+use crate::cfg::{Coloring, Density, Sampling, SceneCfg, SizeVariation};
 use crate::prelude::*;
+use crate::raster::Backend;
+use crate::shape::Movable;
+use crate::svg::Effects;
+use crate::tesselate::{Archimedean, CustomTiling, Truchet};
 use rand::{rngs::StdRng, seq::SliceRandom};
 use serde_derive::Deserialize;
 use std::collections::HashMap;
@@ -16,6 +20,7 @@ pub struct MetaConfig {
     pub themes: Option<ConfigThemes>,
     pub shapes: Option<ConfigShapes>,
     pub data: Option<ConfigData>,
+    pub tilings: Option<ConfigCustomTilings>,
     pub entry: Option<Vec<ConfigEntry>>,
 }
 
@@ -28,6 +33,17 @@ pub struct ConfigGlobal {
     pub size: Option<f64>,
     pub width: Option<usize>,
     pub height: Option<usize>,
+    pub themed: Option<bool>,
+    pub grain: Option<f64>,
+    pub blur: Option<f64>,
+    pub blur_pattern: Option<f64>,
+    pub shadow: Option<f64>,
+    pub page_width: Option<f64>,
+    pub page_height: Option<f64>,
+    pub renderer: Option<String>,
+    pub seamless: Option<bool>,
+    pub coloring: Option<String>,
+    pub coloring_steps: Option<usize>,
 }
 
 /// Lines appearance
@@ -49,6 +65,35 @@ pub struct ConfigLines {
     pub squ_and_tri_color: Option<String>,
     pub pen_width: Option<f64>,
     pub pen_color: Option<String>,
+    pub squ_width: Option<f64>,
+    pub squ_color: Option<String>,
+    pub bri_width: Option<f64>,
+    pub bri_color: Option<String>,
+    pub her_width: Option<f64>,
+    pub her_color: Option<String>,
+    pub bas_width: Option<f64>,
+    pub bas_color: Option<String>,
+    pub vor_width: Option<f64>,
+    pub vor_color: Option<String>,
+    pub kit_width: Option<f64>,
+    pub kit_color: Option<String>,
+    pub p_rho_width: Option<f64>,
+    pub p_rho_color: Option<String>,
+    pub hat_width: Option<f64>,
+    pub hat_color: Option<String>,
+    pub arc_width: Option<f64>,
+    pub arc_color: Option<String>,
+    pub lav_width: Option<f64>,
+    pub lav_color: Option<String>,
+    pub ada_width: Option<f64>,
+    pub ada_color: Option<String>,
+    pub tru_width: Option<f64>,
+    pub tru_color: Option<String>,
+    pub cub_width: Option<f64>,
+    pub cub_color: Option<String>,
+    pub inset: Option<f64>,
+    pub corner_radius: Option<f64>,
+    pub grout_color: Option<String>,
 }
 
 /// Color list
@@ -72,6 +117,38 @@ pub struct ConfigShapes {
     pub list: Map<String, Value>,
 }
 
+/// Tilings declared in the configuration
+#[derive(Deserialize, Default, Debug)]
+pub struct ConfigCustomTilings {
+    pub custom: Option<Map<String, Value>>,
+}
+
+/// A tiling given by the prototiles of one period and the two directions of the period.
+/// Lengths are in units of the tile size.
+#[derive(Deserialize, Debug)]
+pub struct ConfigCustomTiling {
+    pub tiles: Vec<ConfigPrototile>,
+    pub idir: [f64; 2],
+    pub jdir: [f64; 2],
+    pub size: Option<f64>,
+    /// Line width, overrides `[lines] width`
+    pub width: Option<f64>,
+    /// Line color, overrides `[lines] color`
+    pub color: Option<String>,
+}
+
+/// A prototile, either by its vertices or by its angles and sides (as `Movable::polygon`),
+/// then turned by `rot` degrees and moved by `at`
+#[derive(Deserialize, Debug)]
+pub struct ConfigPrototile {
+    pub vertices: Option<Vec<[f64; 2]>>,
+    pub angles: Option<Vec<f64>>,
+    pub sides: Option<Vec<f64>>,
+    pub at: Option<[f64; 2]>,
+    pub rot: Option<f64>,
+    pub kind: Option<usize>,
+}
+
 /// Group together pattern options and tiling options
 #[derive(Deserialize, Default, Debug)]
 pub struct ConfigData {
@@ -88,7 +165,30 @@ pub struct ConfigTilings {
     pub size_squ_and_tri: Option<f64>,
     pub size_rho: Option<f64>,
     pub size_pen: Option<f64>,
+    pub size_squ: Option<f64>,
+    pub size_bri: Option<f64>,
+    pub size_her: Option<f64>,
+    pub size_bas: Option<f64>,
+    pub size_kit: Option<f64>,
+    pub size_p_rho: Option<f64>,
+    pub size_hat: Option<f64>,
+    pub size_arc: Option<f64>,
+    pub size_lav: Option<f64>,
+    pub size_ada: Option<f64>,
+    pub size_tru: Option<f64>,
+    pub size_cub: Option<f64>,
     pub nb_delaunay: Option<usize>,
+    pub nb_voronoi: Option<usize>,
+    pub lloyd_voronoi: Option<usize>,
+    pub sampling: Option<String>,
+    pub poisson_distance: Option<f64>,
+    pub poisson_density: Option<String>,
+    pub truchet_orientation: Option<String>,
+    pub jitter_amplitude: Option<f64>,
+    pub jitter_smoothness: Option<f64>,
+    pub size_field: Option<String>,
+    pub size_ratio: Option<f64>,
+    pub kind_shade: Option<isize>,
 }
 
 /// Pattern options
@@ -131,9 +231,32 @@ impl MetaConfig {
 
     /// Choose options at random according to configuration
     pub fn pick_cfg(self, rng: &mut StdRng, time: u64) -> SceneCfg {
+        // Rasterizer for PNG output
+        let backend = match self.global.as_ref().and_then(|g| g.renderer.as_deref()) {
+            Some("native") => Backend::Native,
+            _ => Backend::Svg,
+        };
+        let steps = self.global.as_ref().and_then(|g| g.coloring_steps);
+        let coloring = match self.global.as_ref().and_then(|g| g.coloring.as_deref()) {
+            Some("contrast") => Coloring::Contrast,
+            Some("palette") => Coloring::Palette,
+            Some("smooth") => Coloring::Smooth,
+            Some("life") => Coloring::Life(steps.unwrap_or(LIFE_STEPS)),
+            Some("reaction-diffusion") => {
+                Coloring::ReactionDiffusion(steps.unwrap_or(REACTION_DIFFUSION_STEPS))
+            }
+            _ => Coloring::Independent,
+        };
+        // Image that repeats without seams
+        let seamless = self
+            .global
+            .as_ref()
+            .and_then(|g| g.seamless)
+            .unwrap_or(false);
+
         // Read default/overriden global options
-        let (deviation, distance, size, width, height) = {
-            let (deviation, distance, size, width, height);
+        let (deviation, distance, size, width, height, themed, effects, page_size) = {
+            let (deviation, distance, size, width, height, themed, effects, page_size);
             match self.global {
                 None => {
                     deviation = DEVIATION;
@@ -141,6 +264,9 @@ impl MetaConfig {
                     size = SIZE;
                     width = WIDTH;
                     height = HEIGHT;
+                    themed = false;
+                    effects = Effects::default();
+                    page_size = None;
                 }
                 Some(g) => {
                     match g.deviation {
@@ -179,9 +305,25 @@ impl MetaConfig {
                             height = s;
                         }
                     }
+                    themed = g.themed.unwrap_or(false);
+                    effects = Effects {
+                        grain: g.grain.unwrap_or(0.),
+                        blur: g.blur.unwrap_or(0.),
+                        blur_pattern: g.blur_pattern.unwrap_or(0.),
+                        shadow: g.shadow.unwrap_or(0.),
+                    };
+                    page_size = match (g.page_width, g.page_height) {
+                        (Some(w), Some(h)) => Some((w, h)),
+                        // Keep the aspect ratio of the image when only one side is given
+                        (Some(w), None) => Some((w, w * height as f64 / width as f64)),
+                        (None, Some(h)) => Some((h * width as f64 / height as f64, h)),
+                        (None, None) => None,
+                    };
                 }
             }
-            (deviation, distance, size, width, height)
+            (
+                deviation, distance, size, width, height, themed, effects, page_size,
+            )
         };
 
         // Get list of named colors
@@ -216,24 +358,72 @@ impl MetaConfig {
             themes
         };
 
+        // Tilings declared in the configuration, usable by name in shapes
+        let (custom_names, custom_tilings, custom_sizes, custom_lines) = {
+            let (mut names, mut tilings, mut sizes, mut lines) =
+                (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+            if let Some(ConfigCustomTilings { custom: Some(list) }) = self.tilings {
+                for (name, val) in list.iter() {
+                    match custom_tiling_from_value(val) {
+                        Ok((tiling, cfg)) => {
+                            names.push(name.clone());
+                            tilings.push(tiling);
+                            sizes.push(cfg.size);
+                            lines.push((cfg.width, cfg.color));
+                        }
+                        Err(e) => println!("{} is not a valid tiling: {}", name, e),
+                    }
+                }
+            }
+            (names, tilings, sizes, lines)
+        };
+
         // List of allowed shape combinations
         let shapes = {
             let mut shapes = HashMap::new();
             if let Some(ConfigShapes { list }) = self.shapes {
                 for name in list.keys() {
-                    shapes.insert(name.clone(), shapes_from_value(&list[name], &shapes));
+                    let sh = shapes_from_value(&list[name], &shapes, &custom_names);
+                    shapes.insert(name.clone(), sh);
                 }
             }
             shapes
         };
 
         let (theme, shape, line_color_override) = choose_theme_shapes(rng, &self.entry, time);
+        // Aperiodic tilings can't wrap around, they are left out of seamless images,
+        // and so are the patterns that are not bounded
+        let fallback_tiling = |rng: &mut StdRng| loop {
+            let tiling = Tiling::choose(rng);
+            if !seamless || tiling.is_periodic() {
+                break tiling;
+            }
+        };
+        let fallback_pattern = |rng: &mut StdRng| loop {
+            let pattern = Pattern::choose(rng);
+            if !seamless || pattern.is_bounded() {
+                break pattern;
+            }
+        };
         let (tiling, pattern) = match shapes.get(&shape) {
-            None => (Tiling::choose(rng), Pattern::choose(rng)),
-            Some(t) => (
-                t.1.choose(rng).unwrap_or_else(|| Tiling::choose(rng)),
-                t.0.choose(rng).unwrap_or_else(|| Pattern::choose(rng)),
-            ),
+            None => (fallback_tiling(rng), fallback_pattern(rng)),
+            Some(t) => {
+                let (patterns, tilings) = if seamless {
+                    let mut patterns = t.0.extract();
+                    patterns.retain(|(pattern, _)| pattern.is_bounded());
+                    let mut tilings = t.1.extract();
+                    tilings.retain(|(tiling, _)| tiling.is_periodic());
+                    (Chooser::new(patterns), Chooser::new(tilings))
+                } else {
+                    (t.0.clone(), t.1.clone())
+                };
+                (
+                    tilings.choose(rng).unwrap_or_else(|| fallback_tiling(rng)),
+                    patterns
+                        .choose(rng)
+                        .unwrap_or_else(|| fallback_pattern(rng)),
+                )
+            }
         };
 
         // Get pattern-specific information according to picked shapes
@@ -344,6 +534,68 @@ impl MetaConfig {
         }
 
         // Get tiling-specific options according to picked shapes
+        let (lloyd_iterations, kind_shade) = match &self.data {
+            Some(ConfigData {
+                patterns: _,
+                tilings: Some(t),
+            }) => (
+                t.lloyd_voronoi.unwrap_or(LLOYD_VORONOI),
+                t.kind_shade.unwrap_or(KIND_SHADE),
+            ),
+            _ => (LLOYD_VORONOI, KIND_SHADE),
+        };
+        let sampling = match &self.data {
+            Some(ConfigData {
+                patterns: _,
+                tilings: Some(t),
+            }) if t.sampling.as_deref() == Some("poisson") => {
+                let density = match t.poisson_density.as_deref() {
+                    Some("center") => Density::Center,
+                    Some("boundaries") => Density::Boundaries,
+                    _ => Density::Uniform,
+                };
+                Sampling::Poisson(t.poisson_distance.filter(|d| *d > 0.), density)
+            }
+            _ => Sampling::Uniform,
+        };
+        let (jitter, jitter_smoothness) = match &self.data {
+            Some(ConfigData {
+                patterns: _,
+                tilings: Some(t),
+            }) => (
+                t.jitter_amplitude.unwrap_or(JITTER_AMPLITUDE),
+                t.jitter_smoothness.unwrap_or(JITTER_SMOOTHNESS),
+            ),
+            _ => (JITTER_AMPLITUDE, JITTER_SMOOTHNESS),
+        };
+        let truchet_by_pattern = match &self.data {
+            Some(ConfigData {
+                patterns: _,
+                tilings: Some(t),
+            }) => t.truchet_orientation.as_deref() == Some("pattern"),
+            _ => false,
+        };
+        let (size_variation, size_ratio) = match &self.data {
+            Some(ConfigData {
+                patterns: _,
+                tilings: Some(t),
+            }) => (
+                match t.size_field.as_deref() {
+                    Some("radial") => SizeVariation::Radial,
+                    Some("ramp") => SizeVariation::Ramp,
+                    Some("noise") => SizeVariation::Noise,
+                    _ => SizeVariation::Uniform,
+                },
+                t.size_ratio.unwrap_or(SIZE_RATIO).clamp(0.1, 10.),
+            ),
+            _ => (SizeVariation::Uniform, SIZE_RATIO),
+        };
+        let size_variation = if seamless && !matches!(size_variation, SizeVariation::Uniform) {
+            println!("The size of the tiles can't vary on a seamless image");
+            SizeVariation::Uniform
+        } else {
+            size_variation
+        };
         let (size_tiling, nb_delaunay) = {
             if let Some(ConfigData {
                 patterns: _,
@@ -357,7 +609,21 @@ impl MetaConfig {
                     Tiling::SquaresAndTriangles => (t.size_squ_and_tri.unwrap_or(size), 0),
                     Tiling::Rhombus => (t.size_rho.unwrap_or(size), 0),
                     Tiling::Pentagons(_) => (t.size_pen.unwrap_or(size), 0),
+                    Tiling::Squares => (t.size_squ.unwrap_or(size), 0),
+                    Tiling::Bricks => (t.size_bri.unwrap_or(size), 0),
+                    Tiling::Herringbone => (t.size_her.unwrap_or(size), 0),
+                    Tiling::Basketweave => (t.size_bas.unwrap_or(size), 0),
+                    Tiling::KitesAndDarts => (t.size_kit.unwrap_or(size), 0),
+                    Tiling::PenroseRhombs => (t.size_p_rho.unwrap_or(size), 0),
+                    Tiling::Hats => (t.size_hat.unwrap_or(size), 0),
+                    Tiling::Archimedean(_) => (t.size_arc.unwrap_or(size), 0),
+                    Tiling::Laves(_) => (t.size_lav.unwrap_or(size), 0),
+                    Tiling::Adaptive => (t.size_ada.unwrap_or(size), 0),
+                    Tiling::Truchet(_) => (t.size_tru.unwrap_or(size), 0),
+                    Tiling::Cubes => (t.size_cub.unwrap_or(size), 0),
+                    Tiling::Custom(idx) => (custom_sizes[idx].unwrap_or(size), 0),
                     Tiling::Delaunay => (0.0, t.nb_delaunay.unwrap_or(NB_DELAUNAY)),
+                    Tiling::Voronoi => (0.0, t.nb_voronoi.unwrap_or(NB_VORONOI)),
                 }
             } else {
                 match tiling {
@@ -367,17 +633,43 @@ impl MetaConfig {
                     Tiling::SquaresAndTriangles => (size, 0),
                     Tiling::Rhombus => (size, 0),
                     Tiling::Pentagons(_) => (size, 0),
+                    Tiling::Squares => (size, 0),
+                    Tiling::Bricks => (size, 0),
+                    Tiling::Herringbone => (size, 0),
+                    Tiling::Basketweave => (size, 0),
+                    Tiling::KitesAndDarts => (size, 0),
+                    Tiling::PenroseRhombs => (size, 0),
+                    Tiling::Hats => (size, 0),
+                    Tiling::Archimedean(_) => (size, 0),
+                    Tiling::Laves(_) => (size, 0),
+                    Tiling::Adaptive => (size, 0),
+                    Tiling::Truchet(_) => (size, 0),
+                    Tiling::Cubes => (size, 0),
+                    Tiling::Custom(idx) => (custom_sizes[idx].unwrap_or(size), 0),
                     Tiling::Delaunay => (0.0, NB_DELAUNAY),
+                    Tiling::Voronoi => (0.0, NB_VORONOI),
                 }
             }
         };
-        let (line_width, line_color_default) = {
-            if let Some(lines) = self.lines {
-                lines.get_settings(tiling, &colors)
+        // Tiny tiles would take forever to lay out
+        let size_tiling =
+            if nb_delaunay == 0 && !(size_tiling.is_finite() && size_tiling >= MIN_SIZE) {
+                println!(
+                    "{} is not a valid tile size, it must be at least {}",
+                    size_tiling, MIN_SIZE
+                );
+                SIZE
             } else {
-                (LINE_WIDTH, LINE_COLOR)
-            }
+                size_tiling
+            };
+        let (inset, corner_radius, grout_color) = match &self.lines {
+            Some(lines) => lines.get_style(&colors),
+            None => (0., 0., None),
         };
+        let (line_width, line_color_default) =
+            self.lines
+                .unwrap_or_default()
+                .get_settings(tiling, &colors, &custom_lines);
 
         SceneCfg {
             deviation,
@@ -395,21 +687,36 @@ impl MetaConfig {
                 y: 0,
                 w: width,
                 h: height,
+                seamless,
             },
             tiling,
             line_width,
             line_color: color_from_value(&Value::String(line_color_override), &colors)
-                .unwrap_or_else(|_| {
-                    color_from_value(&Value::String(line_color_default.to_string()), &colors)
-                        .unwrap_or(Color(0, 0, 0))
-                }),
+                .unwrap_or(line_color_default),
+            inset,
+            corner_radius,
+            grout_color,
             pattern,
             nb_pattern,
             var_stripes,
             nb_delaunay,
+            custom_tilings,
+            lloyd_iterations,
+            sampling,
+            truchet_by_pattern,
+            size_variation,
+            size_ratio,
+            jitter,
+            jitter_smoothness,
+            coloring,
+            kind_shade,
             size_tiling,
             width_pattern,
             tightness_spiral,
+            themed,
+            effects,
+            page_size,
+            backend,
         }
     }
 }
@@ -547,20 +854,8 @@ fn theme_item_from_value(val: &Value, dict: &HashMap<String, Color>) -> (ThemeIt
                             };
                             let variability = match tbl.get("variability") {
                                 None => 0,
-                                Some(Value::Integer(n)) => {
-                                    if *n > 0 {
-                                        *n as usize
-                                    } else {
-                                        0
-                                    }
-                                }
-                                Some(Value::Float(f)) => {
-                                    if *f > 0. {
-                                        f.round() as usize
-                                    } else {
-                                        0
-                                    }
-                                }
+                                Some(Value::Integer(n)) if *n > 0 => *n as usize,
+                                Some(Value::Float(f)) if *f > 0. => f.round() as usize,
                                 Some(_v) => 0,
                             };
                             salt.0.push(SaltItem {
@@ -623,6 +918,7 @@ Provide a theme item or an array of theme items",
 fn shapes_from_value(
     val: &Value,
     shapes: &HashMap<String, (Chooser<Pattern>, Chooser<Tiling>)>,
+    custom: &[String],
 ) -> (Chooser<Pattern>, Chooser<Tiling>) {
     let mut tilings = Chooser::new(vec![]);
     let mut patterns = Chooser::new(vec![]);
@@ -636,14 +932,15 @@ fn shapes_from_value(
                             tilings.append(t.extract());
                             patterns.append(p.extract());
                         } else {
-                            add_shape(&s[..], BASE_WEIGHT, &mut tilings, &mut patterns);
+                            add_shape(&s[..], BASE_WEIGHT, custom, &mut tilings, &mut patterns);
                         }
                     }
                     Value::Array(a) => {
                         if a.len() == 2 {
                             match &a[..] {
                                 [Value::String(s), Value::Integer(w)] if *w > 0 => {
-                                    add_shape(&s[..], *w as usize, &mut tilings, &mut patterns)
+                                    let w = *w as usize;
+                                    add_shape(&s[..], w, custom, &mut tilings, &mut patterns)
                                 }
                                 _ => println!("{} is not a valid shape.", x),
                             }
@@ -660,8 +957,14 @@ fn shapes_from_value(
     (patterns, tilings)
 }
 
-/// Read shape from one of its names
-fn add_shape(s: &str, w: usize, tilings: &mut Chooser<Tiling>, patterns: &mut Chooser<Pattern>) {
+/// Read shape from one of its names, or from the name of a custom tiling
+fn add_shape(
+    s: &str,
+    w: usize,
+    custom: &[String],
+    tilings: &mut Chooser<Tiling>,
+    patterns: &mut Chooser<Pattern>,
+) {
     match s {
         "H" | "hex." | "hexagons" => tilings.push(Tiling::Hexagons, w),
         "T" | "tri." | "triangles" => tilings.push(Tiling::Triangles, w),
@@ -669,6 +972,7 @@ fn add_shape(s: &str, w: usize, tilings: &mut Chooser<Tiling>, patterns: &mut Ch
         "S&T" | "squ.&tri." | "squares&triangles" => tilings.push(Tiling::SquaresAndTriangles, w),
         "R" | "rho." | "rhombus" => tilings.push(Tiling::Rhombus, w),
         "D" | "del." | "delaunay" => tilings.push(Tiling::Delaunay, w),
+        "V" | "vor." | "voronoi" => tilings.push(Tiling::Voronoi, w),
         "P" | "pen." | "pentagons" => tilings.push(Tiling::Pentagons(0), w),
         "P1" | "pen.1" | "pentagons-1" => tilings.push(Tiling::Pentagons(1), w),
         "P2" | "pen.2" | "pentagons-2" => tilings.push(Tiling::Pentagons(2), w),
@@ -676,6 +980,82 @@ fn add_shape(s: &str, w: usize, tilings: &mut Chooser<Tiling>, patterns: &mut Ch
         "P4" | "pen.4" | "pentagons-4" => tilings.push(Tiling::Pentagons(4), w),
         "P5" | "pen.5" | "pentagons-5" => tilings.push(Tiling::Pentagons(5), w),
         "P6" | "pen.6" | "pentagons-6" => tilings.push(Tiling::Pentagons(6), w),
+        "P7" | "pen.7" | "pentagons-7" => tilings.push(Tiling::Pentagons(7), w),
+        "P8" | "pen.8" | "pentagons-8" => tilings.push(Tiling::Pentagons(8), w),
+        "P9" | "pen.9" | "pentagons-9" => tilings.push(Tiling::Pentagons(9), w),
+        "P10" | "pen.10" | "pentagons-10" => tilings.push(Tiling::Pentagons(10), w),
+        "P11" | "pen.11" | "pentagons-11" => tilings.push(Tiling::Pentagons(11), w),
+        "P12" | "pen.12" | "pentagons-12" => tilings.push(Tiling::Pentagons(12), w),
+        "P13" | "pen.13" | "pentagons-13" => tilings.push(Tiling::Pentagons(13), w),
+        "P14" | "pen.14" | "pentagons-14" => tilings.push(Tiling::Pentagons(14), w),
+        "P15" | "pen.15" | "pentagons-15" => tilings.push(Tiling::Pentagons(15), w),
+        "S" | "squ." | "squares" => tilings.push(Tiling::Squares, w),
+        "B" | "bri." | "bricks" => tilings.push(Tiling::Bricks, w),
+        "HB" | "her." | "herringbone" => tilings.push(Tiling::Herringbone, w),
+        "BW" | "bas." | "basketweave" => tilings.push(Tiling::Basketweave, w),
+        "KD" | "kit." | "kites&darts" => tilings.push(Tiling::KitesAndDarts, w),
+        "PR" | "p-rho." | "penrose-rhombs" => tilings.push(Tiling::PenroseRhombs, w),
+        "HT" | "hat." | "hats" => tilings.push(Tiling::Hats, w),
+        "A" | "arc." | "archimedean" => tilings.push(Tiling::Archimedean(None), w),
+        "A488" | "arc.4.8.8" | "truncated-square" => {
+            tilings.push(Tiling::Archimedean(Some(Archimedean::TruncatedSquare)), w)
+        }
+        "A31212" | "arc.3.12.12" | "truncated-hexagonal" => tilings.push(
+            Tiling::Archimedean(Some(Archimedean::TruncatedHexagonal)),
+            w,
+        ),
+        "A3464" | "arc.3.4.6.4" | "rhombitrihexagonal" => tilings.push(
+            Tiling::Archimedean(Some(Archimedean::Rhombitrihexagonal)),
+            w,
+        ),
+        "A4612" | "arc.4.6.12" | "truncated-trihexagonal" => tilings.push(
+            Tiling::Archimedean(Some(Archimedean::TruncatedTrihexagonal)),
+            w,
+        ),
+        "A33434" | "arc.3.3.4.3.4" | "snub-square" => {
+            tilings.push(Tiling::Archimedean(Some(Archimedean::SnubSquare)), w)
+        }
+        "A33336" | "arc.3.3.3.3.6" | "snub-hexagonal" => {
+            tilings.push(Tiling::Archimedean(Some(Archimedean::SnubHexagonal)), w)
+        }
+        "A33344" | "arc.3.3.3.4.4" | "elongated-triangular" => tilings.push(
+            Tiling::Archimedean(Some(Archimedean::ElongatedTriangular)),
+            w,
+        ),
+        "L" | "lav." | "laves" => tilings.push(Tiling::Laves(None), w),
+        "L488" | "lav.4.8.8" | "tetrakis-square" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::TruncatedSquare)), w)
+        }
+        "L31212" | "lav.3.12.12" | "triakis-triangular" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::TruncatedHexagonal)), w)
+        }
+        "L3464" | "lav.3.4.6.4" | "deltoidal-trihexagonal" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::Rhombitrihexagonal)), w)
+        }
+        "L4612" | "lav.4.6.12" | "kisrhombille" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::TruncatedTrihexagonal)), w)
+        }
+        "L33434" | "lav.3.3.4.3.4" | "cairo" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::SnubSquare)), w)
+        }
+        "L33336" | "lav.3.3.3.3.6" | "floret" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::SnubHexagonal)), w)
+        }
+        "L33344" | "lav.3.3.3.4.4" | "prismatic" => {
+            tilings.push(Tiling::Laves(Some(Archimedean::ElongatedTriangular)), w)
+        }
+        "AD" | "ada." | "adaptive" => tilings.push(Tiling::Adaptive, w),
+        "CU" | "cub." | "cubes" => tilings.push(Tiling::Cubes, w),
+        "TR" | "tru." | "truchet" => tilings.push(Tiling::Truchet(None), w),
+        "TRA" | "tru.arc." | "truchet-arcs" => {
+            tilings.push(Tiling::Truchet(Some(Truchet::Arcs)), w)
+        }
+        "TRD" | "tru.dia." | "truchet-diagonals" => {
+            tilings.push(Tiling::Truchet(Some(Truchet::Diagonals)), w)
+        }
+        "TRH" | "tru.hex." | "truchet-hexagons" => {
+            tilings.push(Tiling::Truchet(Some(Truchet::Hexagons)), w)
+        }
         "FC" | "f-cir." | "free-circles" => patterns.push(Pattern::FreeCircles, w),
         "FT" | "f-tri." | "free-triangles" => patterns.push(Pattern::FreeTriangles, w),
         "FR" | "f-str." | "free-stripes" => patterns.push(Pattern::FreeStripes, w),
@@ -685,10 +1065,51 @@ fn add_shape(s: &str, w: usize, tilings: &mut Chooser<Tiling>, patterns: &mut Ch
         "CS" | "c-str." | "crossed-stripes" => patterns.push(Pattern::CrossedStripes, w),
         "PW" | "p-wav." | "parallel-waves" => patterns.push(Pattern::ParallelWaves, w),
         "PT" | "p-saw." | "parallel-sawteeth" => patterns.push(Pattern::ParallelSawteeth, w),
-        _ => println!("{} is not recognized as a shape", s),
+        _ => match custom.iter().position(|name| name == s) {
+            Some(idx) => tilings.push(Tiling::Custom(idx), w),
+            None => println!("{} is not recognized as a shape", s),
+        },
     }
 }
 
+/// Read a custom tiling, along with its options (size and lines)
+fn custom_tiling_from_value(val: &Value) -> Result<(CustomTiling, ConfigCustomTiling), String> {
+    let cfg: ConfigCustomTiling = val.clone().try_into().map_err(|e| format!("{}", e))?;
+    let pos = |[x, y]: [f64; 2]| Pos(x, y);
+    let mut tiles = Vec::new();
+    for (i, tile) in cfg.tiles.iter().enumerate() {
+        let shape = match (&tile.vertices, &tile.angles, &tile.sides) {
+            (Some(v), None, None) if v.len() >= 3 => {
+                Movable::from(v.iter().map(|p| pos(*p)).collect())
+            }
+            (None, Some(angles), Some(sides)) => Movable::polygon(angles, sides)
+                .ok_or_else(|| format!("tile {} has inconsistent angles and sides", i))?,
+            _ => {
+                return Err(format!(
+                    "tile {} needs either 3 vertices or more, or angles and sides",
+                    i
+                ))
+            }
+        };
+        let shape = shape.rotated(tile.rot.unwrap_or(0.));
+        let at = pos(tile.at.unwrap_or([0., 0.]));
+        let pts = shape.vertices().iter().map(|p| *p + at).collect();
+        tiles.push((pts, tile.kind));
+    }
+    let (idir, jdir) = (pos(cfg.idir), pos(cfg.jdir));
+    // The period must span an actual area or the lattice would never leave the frame
+    if (idir.0 * jdir.1 - idir.1 * jdir.0).abs() < 1e-3 {
+        return Err(String::from("idir and jdir are parallel"));
+    }
+    if cfg.size.is_some_and(|s| s <= 0.) {
+        return Err(String::from("size must be positive"));
+    }
+    if cfg.width.is_some_and(|w| w < 0.) {
+        return Err(String::from("width must not be negative"));
+    }
+    Ok((CustomTiling { tiles, idir, jdir }, cfg))
+}
+
 fn choose_theme_shapes(
     rng: &mut StdRng,
     entry: &Option<Vec<ConfigEntry>>,
@@ -707,7 +1128,7 @@ fn choose_theme_shapes(
                     .map(String::from)
                     .collect::<Vec<_>>();
                 let start = markers
-                    .get(0)
+                    .first()
                     .as_ref()
                     .unwrap_or(&&String::from("0"))
                     .parse::<usize>()
@@ -752,7 +1173,23 @@ fn choose_theme_shapes(
 }
 
 impl ConfigLines {
-    fn get_settings(&self, tiling: Tiling, colors: &HashMap<String, Color>) -> (f64, Color) {
+    fn get_style(&self, colors: &HashMap<String, Color>) -> (f64, f64, Option<Color>) {
+        (
+            self.inset.unwrap_or(0.).max(0.),
+            self.corner_radius.unwrap_or(0.).max(0.),
+            self.grout_color
+                .as_ref()
+                .and_then(|c| color_from_value(&Value::String(c.to_string()), colors).ok()),
+        )
+    }
+
+    /// Line width and color for a tiling, custom tilings carry their own in `custom`
+    fn get_settings(
+        &self,
+        tiling: Tiling,
+        colors: &HashMap<String, Color>,
+        custom: &[(Option<f64>, Option<String>)],
+    ) -> (f64, Color) {
         let (w, c) = match tiling {
             Tiling::Hexagons => (self.hex_width, &self.hex_color),
             Tiling::Triangles => (self.tri_width, &self.tri_color),
@@ -760,7 +1197,21 @@ impl ConfigLines {
             Tiling::SquaresAndTriangles => (self.squ_and_tri_width, &self.squ_and_tri_color),
             Tiling::Rhombus => (self.rho_width, &self.rho_color),
             Tiling::Pentagons(_) => (self.pen_width, &self.pen_color),
+            Tiling::Squares => (self.squ_width, &self.squ_color),
+            Tiling::Bricks => (self.bri_width, &self.bri_color),
+            Tiling::Herringbone => (self.her_width, &self.her_color),
+            Tiling::Basketweave => (self.bas_width, &self.bas_color),
             Tiling::Delaunay => (self.del_width, &self.del_color),
+            Tiling::Voronoi => (self.vor_width, &self.vor_color),
+            Tiling::KitesAndDarts => (self.kit_width, &self.kit_color),
+            Tiling::PenroseRhombs => (self.p_rho_width, &self.p_rho_color),
+            Tiling::Hats => (self.hat_width, &self.hat_color),
+            Tiling::Archimedean(_) => (self.arc_width, &self.arc_color),
+            Tiling::Laves(_) => (self.lav_width, &self.lav_color),
+            Tiling::Adaptive => (self.ada_width, &self.ada_color),
+            Tiling::Truchet(_) => (self.tru_width, &self.tru_color),
+            Tiling::Cubes => (self.cub_width, &self.cub_color),
+            Tiling::Custom(idx) => (custom[idx].0, &custom[idx].1),
         };
         (
             w.unwrap_or_else(|| self.width.unwrap_or(LINE_WIDTH)),
@@ -782,6 +1233,7 @@ impl ConfigLines {
 const DEVIATION: usize = 20;
 const DISTANCE: usize = 40;
 const SIZE: f64 = 15.;
+const MIN_SIZE: f64 = 1.;
 const WIDTH: usize = 1000;
 const HEIGHT: usize = 600;
 const NB_FREE_CIRCLES: usize = 10;
@@ -801,5 +1253,76 @@ const WIDTH_WAVE: f64 = 0.3;
 const WIDTH_SAWTOOTH: f64 = 0.3;
 const TIGHTNESS_SPIRAL: f64 = 0.5;
 const NB_DELAUNAY: usize = 1000;
+const NB_VORONOI: usize = 600;
+const LLOYD_VORONOI: usize = 2;
+const KIND_SHADE: isize = 25;
+const JITTER_AMPLITUDE: f64 = 0.;
+const JITTER_SMOOTHNESS: f64 = 2.;
+const SIZE_RATIO: f64 = 0.4;
+const LIFE_STEPS: usize = 12;
+const REACTION_DIFFUSION_STEPS: usize = 3000;
 const LINE_WIDTH: f64 = 1.0;
 const LINE_COLOR: Color = Color(0, 0, 0);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn pick_only(shapes: &str, seamless: bool, id: u64) -> SceneCfg {
+        let toml = format!(
+            "[global]\nseamless = {}\n[shapes]\nonly = [{}]\n[[entry]]\nshapes = [\"only\"]\n",
+            seamless, shapes
+        );
+        let mut rng = StdRng::seed_from_u64(id);
+        MetaConfig::from_string(toml).pick_cfg(&mut rng, id)
+    }
+
+    fn pick_tiling(shapes: &str, seamless: bool, id: u64) -> Tiling {
+        pick_only(shapes, seamless, id).tiling
+    }
+
+    #[test]
+    fn seamless_images_leave_out_aperiodic_tilings() {
+        for id in 0..20 {
+            assert!(pick_tiling("\"KD\", \"HT\", \"S\"", true, id).is_periodic());
+            // Nothing left to pick from: fall back to any periodic tiling
+            assert!(pick_tiling("\"PR\"", true, id).is_periodic());
+        }
+        assert!((0..20).any(|id| !pick_tiling("\"KD\", \"S\"", false, id).is_periodic()));
+    }
+
+    #[test]
+    fn seamless_images_leave_out_unbounded_patterns() {
+        let pattern = |shapes, seamless, id| pick_only(shapes, seamless, id).pattern;
+        for id in 0..20 {
+            assert!(pattern("\"PS\", \"FP\", \"CC\"", true, id).is_bounded());
+            // Nothing left to pick from: fall back to any bounded pattern
+            assert!(pattern("\"PW\"", true, id).is_bounded());
+        }
+        assert!((0..20).any(|id| !pattern("\"PS\", \"CC\"", false, id).is_bounded()));
+    }
+
+    #[test]
+    fn custom_tilings_have_their_own_lines() {
+        let toml = "[lines]\nwidth = 1.0\ncolor = \"#000000\"\n\
+                    [tilings.custom.grid]\nidir = [1.0, 0.0]\njdir = [0.0, 1.0]\n\
+                    width = 3.5\ncolor = \"#FF8000\"\n\
+                    tiles = [{ vertices = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] }]\n\
+                    [tilings.custom.plain]\nidir = [1.0, 0.0]\njdir = [0.0, 1.0]\n\
+                    tiles = [{ vertices = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] }]\n\
+                    [shapes]\ngrid = [\"grid\"]\nplain = [\"plain\"]\n";
+        let pick = |shape: &str| {
+            let toml = format!("{}[[entry]]\nshapes = [\"{}\"]\n", toml, shape);
+            let mut rng = StdRng::seed_from_u64(0);
+            MetaConfig::from_string(toml).pick_cfg(&mut rng, 0)
+        };
+        let cfg = pick("grid");
+        assert!(matches!(cfg.tiling, Tiling::Custom(_)));
+        assert_eq!((cfg.line_width, cfg.line_color), (3.5, Color(255, 128, 0)));
+        // Without options of its own, a custom tiling uses the common ones
+        let cfg = pick("plain");
+        assert!(matches!(cfg.tiling, Tiling::Custom(_)));
+        assert_eq!((cfg.line_width, cfg.line_color), (1.0, Color(0, 0, 0)));
+    }
+}
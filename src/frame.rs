@@ -6,6 +6,8 @@ pub struct Frame {
     pub y: usize,
     pub w: usize,
     pub h: usize,
+    /// Opposite edges meet: the image repeats with the frame as its period
+    pub seamless: bool,
 }
 
 impl Frame {
@@ -19,13 +21,32 @@ impl Frame {
         Pos((self.x + self.w / 2) as f64, (self.y + self.h / 2) as f64)
     }
 
+    /// Point brought back inside the frame when it is seamless, unchanged otherwise
+    pub fn wrap(&self, p: Pos) -> Pos {
+        if !self.seamless {
+            return p;
+        }
+        let (w, h) = (self.w as f64, self.h as f64);
+        let (x, y) = (self.x as f64, self.y as f64);
+        Pos(x + (p.0 - x).rem_euclid(w), y + (p.1 - y).rem_euclid(h))
+    }
+
     /// Check that point is within some distance of the frame (include points that are not far outside)
     pub fn is_inside(&self, pos: Pos) -> bool {
+        let (lo, hi) = self.bounds();
+        lo.0 < pos.0 && pos.0 < hi.0 && lo.1 < pos.1 && pos.1 < hi.1
+    }
+
+    /// Top-left and bottom-right corners of the frame, extended by the margin of `is_inside`
+    pub fn bounds(&self) -> (Pos, Pos) {
         let xerr = (self.w as f64) / 10.;
         let yerr = (self.h as f64) / 10.;
-        (self.x as f64 - xerr) < pos.0
-            && pos.0 < (self.x + self.w) as f64 + xerr
-            && (self.y as f64 - yerr) < pos.1
-            && pos.1 < (self.y + self.h) as f64 + yerr
+        (
+            Pos(self.x as f64 - xerr, self.y as f64 - yerr),
+            Pos(
+                (self.x + self.w) as f64 + xerr,
+                (self.y + self.h) as f64 + yerr,
+            ),
+        )
     }
 }
@@ -1,8 +1,17 @@
 use image::GenericImageView;
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::io::Write;
 use thiserror::Error;
 
-use crate::{deserializer::MetaConfig, scene::Scene, svg::Document};
+use crate::{
+    cfg::{Coloring, SceneCfg},
+    coloring::recolor,
+    deserializer::MetaConfig,
+    prelude::*,
+    raster::Canvas,
+    scene::Scene,
+    svg::{Document, Palette, Path, SvgStream, Themed},
+};
 
 #[derive(Error, Debug)]
 pub enum GenImagError {
@@ -10,50 +19,447 @@ pub enum GenImagError {
     CantSaveGeneratedImage(#[from] std::io::Error),
     #[error("can't open image: {0}")]
     CantOpenImage(#[from] image::ImageError),
+    #[error("can't read configuration: {0}")]
+    CantReadConfig(std::io::Error),
 }
 
 /// Generate an image and its blurashs counterpart, from a given id.
 /// If no id is given, then a random one is computed.
+/// Options are read from the TOML file at `config`, defaults are used without one.
 ///
 /// # Errors
 ///
-/// Failed if image can't be written, read or generated
+/// Failed if the configuration can't be read, or image can't be written, read or generated
 pub fn generate_images(
     id: Option<u64>,
+    config: Option<&str>,
     gen_dest: &str,
     blur_dest: &str,
 ) -> Result<String, GenImagError> {
+    let (mut rng, cfg, scene) = setup(id, config)?;
+
+    // Generate document
+    let mut document = Document::new(cfg.frame)
+        .with_effects(cfg.effects)
+        .with_page_size(cfg.page_size)
+        .with_backend(cfg.backend);
+    if cfg.themed {
+        document = document.with_palette(palette(&cfg, &scene));
+    }
+    if let Some(grout) = cfg.grout(&scene) {
+        document.add(grout);
+    }
+    paint_tiles(&cfg, &scene, &mut rng, |elem| document.add(elem));
+
+    document.save(gen_dest)?;
+
+    if gen_dest.ends_with(".png") || gen_dest.ends_with(".png.tmp") {
+        let img = image::open(gen_dest)?;
+        let (width, height) = img.dimensions();
+        save_blur(
+            &img.into_rgba8().into_vec(),
+            (width, height),
+            cfg.frame,
+            blur_dest,
+        )
+    } else {
+        // Vector formats can't be read back
+        let mut thumb = Thumbnail::new(cfg.frame);
+        document.items.iter().for_each(|elem| thumb.add(elem));
+        thumb.save_blur(blur_dest)
+    }
+}
+
+/// Generate the SVG for an id, writing each tile as soon as it is produced,
+/// and its blurhash counterpart as `generate_images` does.
+/// Memory use does not depend on the image size, with two exceptions:
+/// neighbour-aware colorings hold the whole tiling before the first tile is written,
+/// and `Effects::blur_pattern` needs the background tiles first, so the whole
+/// document is built before being written.
+///
+/// # Errors
+///
+/// Failed if the configuration can't be read, or the output or the blur image can't be written
+pub fn stream_svg<W: Write>(
+    id: Option<u64>,
+    config: Option<&str>,
+    mut out: W,
+    blur_dest: &str,
+) -> Result<(W, String), GenImagError> {
+    let (mut rng, cfg, scene) = setup(id, config)?;
+    let palette = palette(&cfg, &scene);
+    let mut thumb = Thumbnail::new(cfg.frame);
+    if cfg.effects.blur_pattern > 0. {
+        let mut document = Document::new(cfg.frame).with_effects(cfg.effects);
+        if cfg.themed {
+            document = document.with_palette(palette);
+        }
+        if let Some(grout) = cfg.grout(&scene) {
+            document.add(grout);
+        }
+        paint_tiles(&cfg, &scene, &mut rng, |elem| document.add(elem));
+        document.items.iter().for_each(|elem| thumb.add(elem));
+        if cfg.themed {
+            write!(out, "{}", Themed(&document))?;
+        } else {
+            write!(out, "{}", document)?;
+        }
+        out.flush()?;
+        return Ok((out, thumb.save_blur(blur_dest)?));
+    }
+    let mut stream = SvgStream::new(
+        out,
+        cfg.frame,
+        if cfg.themed { Some(&palette) } else { None },
+        cfg.effects,
+    )?;
+    let mut res = match cfg.grout(&scene) {
+        Some(grout) => {
+            thumb.add(&grout);
+            stream.add(&grout)
+        }
+        None => Ok(()),
+    };
+    paint_tiles(&cfg, &scene, &mut rng, |elem| {
+        thumb.add(&elem);
+        if res.is_ok() {
+            res = stream.add(&elem);
+        }
+    });
+    res?;
+    let out = stream.finish()?;
+    Ok((out, thumb.save_blur(blur_dest)?))
+}
+
+/// Largest side of the picture the blurhash of vector output is computed from
+const THUMBNAIL_SIZE: usize = 64;
+
+/// Small picture of the tiles drawn as they are produced, to compute a blurhash
+/// without keeping a full size image. Filter effects are left out.
+struct Thumbnail {
+    canvas: Canvas,
+    frame: Frame,
+    scale: f64,
+}
+
+impl Thumbnail {
+    fn new(frame: Frame) -> Self {
+        let scale = (THUMBNAIL_SIZE as f64 / frame.w.max(frame.h) as f64).min(1.);
+        let size = |n: usize| ((n as f64 * scale).round() as usize).max(1);
+        Self {
+            canvas: Canvas::new(size(frame.w), size(frame.h)),
+            frame,
+            scale,
+        }
+    }
+
+    fn add(&mut self, elem: &Path) {
+        let origin = Pos(self.frame.x as f64, self.frame.y as f64);
+        self.canvas.draw(elem, origin, self.scale);
+    }
+
+    fn save_blur(self, blur_dest: &str) -> Result<String, GenImagError> {
+        let size = (self.canvas.w as u32, self.canvas.h as u32);
+        save_blur(&self.canvas.into_rgba(), size, self.frame, blur_dest)
+    }
+}
+
+/// Compute the blurhash of an RGBA picture and save it decoded at the size of the frame
+fn save_blur(
+    rgba: &[u8],
+    (w, h): (u32, u32),
+    frame: Frame,
+    blur_dest: &str,
+) -> Result<String, GenImagError> {
+    let blurhash = blurhash::encode(4, 3, w, h, rgba);
+    let (width, height) = (frame.w as u32, frame.h as u32);
+    let pixels = blurhash::decode(blurhash.as_str(), width, height, 1.2);
+
+    image::save_buffer(blur_dest, &pixels, width, height, image::ColorType::Rgba8)?;
+
+    Ok(blurhash)
+}
+
+/// Pick configuration and scene from an id (random if none is given),
+/// with the options of the TOML file at `config` if any
+pub(crate) fn setup(
+    id: Option<u64>,
+    config: Option<&str>,
+) -> Result<(StdRng, SceneCfg, Scene), GenImagError> {
     let id = id.unwrap_or_else(|| {
         let mut rng = rand::thread_rng();
         rng.gen()
     });
+    let src = match config {
+        Some(path) => std::fs::read_to_string(path).map_err(GenImagError::CantReadConfig)?,
+        None => String::new(),
+    };
 
     let mut rng = StdRng::seed_from_u64(id);
-    let cfg = MetaConfig::from_string(String::new()).pick_cfg(&mut rng, id);
+    let cfg = MetaConfig::from_string(src).pick_cfg(&mut rng, id);
     let scene = Scene::new(&cfg, &mut rng);
-    let stroke = cfg.line_color;
-    let stroke_width = cfg.line_width;
-    let stroke_like_fill = stroke_width < 0.0001;
+    Ok((rng, cfg, scene))
+}
 
-    // Generate document
-    let mut document = Document::new(cfg.frame);
-    for (pos, elem) in cfg.make_tiling(&mut rng) {
-        let fill = scene.color(pos, &mut rng);
-        document.add(
-            elem.with_fill_color(fill)
-                .with_stroke_color(if stroke_like_fill { fill } else { stroke })
-                .with_stroke_width(stroke_width.max(0.1)),
+/// Lay out and color the tiles. Colorings that look at the neighbours
+/// need the whole tiling before the first tile is handed over.
+pub(crate) fn paint_tiles<F>(cfg: &SceneCfg, scene: &Scene, rng: &mut StdRng, mut f: F)
+where
+    F: FnMut(Path),
+{
+    if let Coloring::Independent = cfg.coloring {
+        cfg.for_each_tile(scene, rng, |rng, pos, elem| {
+            f(paint(cfg, scene, rng, pos, elem))
+        });
+        return;
+    }
+    let (tiles, graph) = cfg.make_tiling_graph(scene, rng);
+    let node = tiles.iter().map(|t| t.node).collect::<Vec<_>>();
+    // The first tile of each node is recolored, its copies then take the same colors
+    let mut first = Vec::with_capacity(graph.len());
+    let mut copies = Vec::with_capacity(tiles.len());
+    for (i, tile) in tiles.into_iter().enumerate() {
+        let path = match tile.rng {
+            Some(mut tile_rng) => paint(cfg, scene, &mut tile_rng, tile.pos, tile.path),
+            None => paint(cfg, scene, rng, tile.pos, tile.path),
+        };
+        if node[i] == first.len() {
+            first.push(path);
+            copies.push(None);
+        } else {
+            copies.push(Some(path));
+        }
+    }
+    let base = |elem: &Path| base_color(cfg, scene, elem);
+    let theme = |rng: &mut StdRng| cfg.choose_color(rng);
+    recolor(cfg.coloring, &mut first, &graph, &base, &theme, rng);
+    let colors = first
+        .iter()
+        .map(|p| (p.fill_color, p.stroke_color, p.tint))
+        .collect::<Vec<_>>();
+    let mut first = first.into_iter();
+    for (copy, node) in copies.into_iter().zip(node) {
+        match copy {
+            Some(mut path) => {
+                (path.fill_color, path.stroke_color, path.tint) = colors[node];
+                f(path)
+            }
+            None => f(first.next().unwrap()),
+        }
+    }
+}
+
+/// Theme colors of the scene and the shift of each kind of tile, for themed output
+fn palette(cfg: &SceneCfg, scene: &Scene) -> Palette {
+    Palette {
+        kinds: cfg.kind_shifts(),
+        ..scene.palette()
+    }
+}
+
+/// Color of a tile before any random variation: the base of its scene item, shaded by kind
+fn base_color(cfg: &SceneCfg, scene: &Scene, elem: &Path) -> Color {
+    let c = match elem.tint.map_or(0, |t| t.index) {
+        0 => scene.bg.base(),
+        index => scene.items[index - 1].color_item().base(),
+    };
+    match elem.kind {
+        Some(kind) => c.shift(cfg.kind_shift(kind)),
+        None => c,
+    }
+}
+
+/// Color a tile according to the scene
+fn paint(cfg: &SceneCfg, scene: &Scene, rng: &mut StdRng, pos: Pos, elem: Path) -> Path {
+    let stroke_like_fill = cfg.line_width < 0.0001;
+    let (mut fill, tint) = scene.tint(pos, rng);
+    // Tell apart the prototiles of tilings that have several.
+    // Themed output applies the shift of the kind on top of the tint.
+    if let Some(kind) = elem.kind {
+        fill = fill.shift(cfg.kind_shift(kind));
+    }
+    elem.with_fill_color(fill)
+        .with_tint(Some(tint))
+        .with_stroke_color(if stroke_like_fill {
+            fill
+        } else {
+            cfg.line_color
+        })
+        .with_stroke_width(cfg.line_width.max(0.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixels that differ by more than this are counted as a mismatch
+    const MAX_PIXEL_DIFF: u8 = 16;
+
+    /// Configuration and scene of a small seamless image
+    fn seamless_setup(
+        tiling: &str,
+        pattern: &str,
+        coloring: &str,
+        id: u64,
+    ) -> (StdRng, SceneCfg, Scene) {
+        let toml = format!(
+            "[global]\nwidth = 200\nheight = 150\nsize = 12\nseamless = true\n\
+             coloring = \"{}\"\n\
+             [shapes]\nonly = [\"{}\", \"{}\"]\n[[entry]]\nshapes = [\"only\"]\n",
+            coloring, tiling, pattern
         );
+        let mut rng = StdRng::seed_from_u64(id);
+        let cfg = MetaConfig::from_string(toml).pick_cfg(&mut rng, id);
+        let scene = Scene::new(&cfg, &mut rng);
+        (rng, cfg, scene)
     }
 
-    document.save(gen_dest)?;
+    /// Render a seamless image, then the same tiles with the frame moved by a twentieth
+    /// of its size. Past the right and bottom edges of the first image, the moved one
+    /// shows tiles that wrapped around: they must match the left and top of the first image.
+    /// Returns how many of those pixels do not.
+    fn seam_mismatch(tiling: &str, pattern: &str, coloring: &str, id: u64) -> usize {
+        let (mut rng, cfg, scene) = seamless_setup(tiling, pattern, coloring, id);
+        let frame = cfg.frame;
+        let (dx, dy) = (frame.w / 20, frame.h / 20);
+        let mut first = Canvas::new(frame.w, frame.h);
+        let mut moved = Canvas::new(frame.w, frame.h);
+        let origin = Pos(frame.x as f64, frame.y as f64);
+        paint_tiles(&cfg, &scene, &mut rng, |path| {
+            first.draw(&path, origin, 1.);
+            moved.draw(&path, origin + (dx as f64, dy as f64), 1.);
+        });
+        let (first, moved) = (first.into_rgba(), moved.into_rgba());
+        let (w, h) = (frame.w, frame.h);
+        let mut mismatch = 0;
+        for y in 0..h {
+            for x in 0..w {
+                if x + dx < w && y + dy < h {
+                    continue;
+                }
+                let a = ((y + dy) % h * w + (x + dx) % w) * 4;
+                let b = (y * w + x) * 4;
+                if (0..4).any(|k| first[a + k].abs_diff(moved[b + k]) > MAX_PIXEL_DIFF) {
+                    mismatch += 1;
+                }
+            }
+        }
+        mismatch
+    }
 
-    let img = image::open(gen_dest)?;
-    let (width, height) = img.dimensions();
-    let blurhash = blurhash::encode(4, 3, width, height, &img.into_rgba8().into_vec());
-    let pixels = blurhash::decode(blurhash.as_str(), width, height, 1.2);
+    #[test]
+    fn seamless_images_wrap_around() {
+        let shapes = [
+            "H", "T", "H&T", "S&T", "R", "S", "B", "HB", "BW", "A", "L", "AD", "CU", "TR", "P1",
+            "P2", "P3", "P4", "P5", "P6", "P7", "P8", "P9", "P10", "P11", "P12", "P13", "P14",
+            "P15",
+        ];
+        for shape in shapes {
+            for id in 0..4 {
+                assert_eq!(
+                    seam_mismatch(shape, "FC", "independent", id),
+                    0,
+                    "{} with id {}",
+                    shape,
+                    id
+                );
+            }
+        }
+        // Patterns that reach across the frame are replaced by bounded ones
+        let patterns = ["FC", "FT", "FR", "FP", "CC", "PS", "CS", "PW", "PT"];
+        for pattern in patterns {
+            for id in 0..4 {
+                assert_eq!(
+                    seam_mismatch("S", pattern, "independent", id),
+                    0,
+                    "{} with id {}",
+                    pattern,
+                    id
+                );
+            }
+        }
+    }
 
-    image::save_buffer(blur_dest, &pixels, width, height, image::ColorType::Rgba8)?;
+    /// Paint a seamless image and count the tiles past its edges that are not colored
+    /// as their copy inside the frame
+    fn copy_mismatch(tiling: &str, coloring: &str, id: u64) -> usize {
+        let (mut rng, cfg, scene) = seamless_setup(tiling, "FC", coloring, id);
+        let frame = cfg.frame;
+        let mut tiles = Vec::new();
+        paint_tiles(&cfg, &scene, &mut rng, |path| {
+            let pts = path.data.flatten();
+            let mid = pts.iter().fold(Pos::zero(), |acc, p| acc + *p) * (1. / pts.len() as f64);
+            tiles.push((mid, path.fill_color));
+        });
+        let inside = |p: Pos| frame.wrap(p).dist(p) < 1e-6;
+        let mut mismatch = 0;
+        for &(p, color) in tiles.iter().filter(|(p, _)| !inside(*p)) {
+            let copy = tiles
+                .iter()
+                .find(|(q, _)| inside(*q) && q.dist(frame.wrap(p)) < 0.01);
+            if copy.is_some_and(|(_, c)| *c != color) {
+                mismatch += 1;
+            }
+        }
+        mismatch
+    }
 
-    Ok(blurhash)
+    #[test]
+    fn seamless_colorings_wrap_around() {
+        for coloring in ["contrast", "palette", "smooth"] {
+            for tiling in ["H", "S", "B", "P3"] {
+                for id in 0..3 {
+                    let msg = format!("{} on {} with id {}", coloring, tiling, id);
+                    assert_eq!(copy_mismatch(tiling, coloring, id), 0, "{}", msg);
+                    assert_eq!(seam_mismatch(tiling, "FC", coloring, id), 0, "{}", msg);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn setup_reads_the_config_file() {
+        let path = std::env::temp_dir().join("backgen_setup_config.toml");
+        std::fs::write(&path, "[global]\nwidth = 123\nheight = 77\n").unwrap();
+        let (_, cfg, _) = setup(Some(42), path.to_str()).unwrap();
+        assert_eq!((cfg.frame.w, cfg.frame.h), (123, 77));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            setup(Some(42), path.to_str()),
+            Err(GenImagError::CantReadConfig(_))
+        ));
+    }
+
+    #[test]
+    fn penrose_kinds_get_distinct_fills() {
+        let toml = "[global]\nwidth = 200\nheight = 150\nsize = 12\n\
+                    [shapes]\nonly = [\"PR\"]\n[[entry]]\nshapes = [\"only\"]\n";
+        for id in 0..4 {
+            let mut rng = StdRng::seed_from_u64(id);
+            let cfg = MetaConfig::from_string(toml.to_string()).pick_cfg(&mut rng, id);
+            let scene = Scene::new(&cfg, &mut rng);
+            assert!(cfg.kind_shade > 0);
+            let palette = palette(&cfg, &scene);
+            assert_eq!(palette.kinds, vec![0, -cfg.kind_shade]);
+
+            let mut document = Document::new(cfg.frame).with_palette(palette);
+            let mut sums = [(0., 0); 2];
+            paint_tiles(&cfg, &scene, &mut rng, |elem| {
+                let Color(r, g, b) = elem.fill_color;
+                let (sum, n) = &mut sums[elem.kind.unwrap()];
+                *sum += (r + g + b) as f64 / 3.;
+                *n += 1;
+                document.add(elem);
+            });
+            // Both kinds are spread over the whole scene, the shaded one is darker on average
+            let [neutral, shaded] = sums.map(|(sum, n)| sum / n as f64);
+            assert!(neutral - shaded > cfg.kind_shade as f64 / 2., "id {}", id);
+
+            let svg = format!("{}", Themed(&document));
+            assert!(svg.contains(" kind-0\""));
+            assert!(svg.contains(" kind-1\""));
+            assert!(svg.contains(&format!("--kind-1: {};", -cfg.kind_shade)));
+            assert!(svg.contains(".kind-1 { --kind: var(--kind-1); }"));
+        }
+    }
 }
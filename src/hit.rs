@@ -0,0 +1,164 @@
+use crate::gen_image::{paint_tiles, setup, GenImagError};
+use crate::pos::{bounding_box, polygon_contains};
+use crate::prelude::*;
+use crate::scene::{Contains, Scene};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Tile of a generated image, as drawn
+pub struct TileHit<'a> {
+    /// Position of the tile in the drawing order, grout aside
+    pub index: usize,
+    /// Outline, arcs being flattened
+    pub polygon: &'a [Pos],
+    pub color: Color,
+    /// Index in `Scene::items` of the item the color comes from, `None` for the background
+    pub item: Option<usize>,
+}
+
+struct Tile {
+    polygon: Vec<Pos>,
+    color: Color,
+    item: Option<usize>,
+}
+
+/// Tiles of the image generated for an id, to tell which one lies under a point
+pub struct TileMap {
+    pub scene: Scene,
+    tiles: Vec<Tile>,
+    /// Tiles whose bounding box meets each cell of a grid
+    grid: HashMap<(i64, i64), Vec<usize>>,
+    cell: f64,
+}
+
+impl TileMap {
+    /// Lay out and color the tiles exactly as `generate_images` does for `id` and `config`
+    ///
+    /// # Errors
+    ///
+    /// Failed if the configuration can't be read
+    pub fn new(id: u64, config: Option<&str>) -> Result<Self, GenImagError> {
+        let (mut rng, cfg, scene) = setup(Some(id), config)?;
+        let mut tiles = Vec::new();
+        paint_tiles(&cfg, &scene, &mut rng, |elem| {
+            tiles.push(Tile {
+                polygon: elem.data.flatten(),
+                color: elem.fill_color,
+                item: elem.tint.and_then(|t| t.index.checked_sub(1)),
+            })
+        });
+        let cell = (cfg.frame.w.max(cfg.frame.h) as f64 / 64.).max(1.);
+        let key = |x: f64| (x / cell).floor() as i64;
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (t, tile) in tiles.iter().enumerate() {
+            let (lo, hi) = bounding_box(&tile.polygon);
+            for i in key(lo.0)..=key(hi.0) {
+                for j in key(lo.1)..=key(hi.1) {
+                    grid.entry((i, j)).or_default().push(t);
+                }
+            }
+        }
+        Ok(Self {
+            scene,
+            tiles,
+            grid,
+            cell,
+        })
+    }
+
+    /// Number of tiles
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Tile drawn at a point, the last one drawn if several overlap.
+    /// On a seamless image the point is first brought back inside the frame.
+    pub fn at(&self, p: Pos) -> Option<TileHit<'_>> {
+        let p = self.scene.frame.wrap(p);
+        let key = |x: f64| (x / self.cell).floor() as i64;
+        self.grid
+            .get(&(key(p.0), key(p.1)))?
+            .iter()
+            .rev()
+            .find(|&&t| polygon_contains(&self.tiles[t].polygon, p))
+            .map(|&t| self.get(t))
+    }
+
+    /// Tile drawn at the center of pixel (x, y)
+    pub fn pixel(&self, x: usize, y: usize) -> Option<TileHit<'_>> {
+        let f = &self.scene.frame;
+        self.at(Pos((f.x + x) as f64 + 0.5, (f.y + y) as f64 + 0.5))
+    }
+
+    /// Tile `index` in the drawing order
+    pub fn get(&self, index: usize) -> TileHit<'_> {
+        let tile = &self.tiles[index];
+        TileHit {
+            index,
+            polygon: &tile.polygon,
+            color: tile.color,
+            item: tile.item,
+        }
+    }
+
+    /// All tiles in the drawing order
+    pub fn tiles(&self) -> impl Iterator<Item = TileHit<'_>> {
+        (0..self.tiles.len()).map(|t| self.get(t))
+    }
+
+    /// Scene item that colored a tile, `None` for the background
+    pub fn item(&self, hit: &TileHit) -> Option<&Rc<dyn Contains>> {
+        hit.item.map(|i| &self.scene.items[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::Document;
+
+    /// Distance from `p` to the outline of `polygon`
+    fn to_outline(polygon: &[Pos], p: Pos) -> f64 {
+        let n = polygon.len();
+        (0..n)
+            .map(|k| {
+                let (a, b) = (polygon[k], polygon[(k + 1) % n]);
+                let ab = b - a;
+                let t = ((p - a).dot(ab) / ab.dot(ab)).clamp(0., 1.);
+                p.dist(a + ab * t)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn pixels_show_the_tile_found_there() {
+        for id in [1, 42, 345] {
+            let map = TileMap::new(id, None).unwrap();
+            let (mut rng, cfg, scene) = setup(Some(id), None).unwrap();
+            let mut doc = Document::new(cfg.frame);
+            paint_tiles(&cfg, &scene, &mut rng, |elem| doc.add(elem));
+            let canvas = doc.rasterize();
+            let rgba = canvas.into_rgba();
+            let f = cfg.frame;
+            let mut checked = 0;
+            for y in (0..f.h).step_by(7) {
+                for x in (0..f.w).step_by(7) {
+                    let hit = map.pixel(x, y).expect("the tiles cover the frame");
+                    let center = Pos((f.x + x) as f64 + 0.5, (f.y + y) as f64 + 0.5);
+                    // Away from the outlines the pixel has the fill color alone
+                    if to_outline(hit.polygon, center) < cfg.line_width + 1. {
+                        continue;
+                    }
+                    let px = &rgba[(y * f.w + x) * 4..][..3];
+                    assert_eq!(px, hit.color.to_rgb(), "id {} at ({}, {})", id, x, y);
+                    checked += 1;
+                }
+            }
+            assert!(checked > 100);
+        }
+    }
+}
@@ -1,12 +1,17 @@
+pub mod adjacency;
 pub mod cfg;
 pub mod chooser;
 pub mod color;
+pub mod coloring;
 pub mod deserializer;
 pub mod frame;
 pub mod gen_image;
+pub mod hit;
 pub mod log;
 pub mod paint;
+pub mod pdf;
 pub mod pos;
+pub mod raster;
 pub mod salt;
 pub mod scene;
 pub mod shape;
@@ -17,9 +22,9 @@ pub mod prelude {
     use super::*;
     pub use cfg::{Pattern, Tiling};
     pub use chooser::Chooser;
-    pub use color::Color;
+    pub use color::{Color, Tint};
     pub use frame::Frame;
-    pub use pos::{radians, Pos};
+    pub use pos::{centroid, radians, Pos};
     pub use salt::{Salt, SaltItem};
 
     use std::collections::HashMap;
@@ -13,7 +13,11 @@ pub struct Logger {
 
 impl fmt::Display for Frame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} {} {} #", self.x, self.y, self.w, self.h)
+        write!(f, "{} {} {} {} ", self.x, self.y, self.w, self.h)?;
+        if self.seamless {
+            write!(f, "seamless ")?;
+        }
+        write!(f, "#")
     }
 }
 
@@ -206,8 +210,24 @@ impl Restore for Frame {
         let y = usize::restore(items);
         let w = usize::restore(items);
         let h = usize::restore(items);
-        assert_eq!(items.next().unwrap(), "#");
-        Self { x, y, w, h }
+        // Older logs have no seamless flag
+        let seamless = match items.next().unwrap() {
+            "seamless" => {
+                assert_eq!(items.next().unwrap(), "#");
+                true
+            }
+            end => {
+                assert_eq!(end, "#");
+                false
+            }
+        };
+        Self {
+            x,
+            y,
+            w,
+            h,
+            seamless,
+        }
     }
 }
 
@@ -0,0 +1,208 @@
+use crate::prelude::*;
+use crate::raster::MITER_LIMIT;
+use crate::svg::*;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Points per millimeter (PDF user space unit is 1/72 inch)
+const PT_PER_MM: f64 = 72. / 25.4;
+/// Points per pixel, assuming a 96 DPI screen
+const PT_PER_PX: f64 = 72. / 96.;
+
+impl Document {
+    /// Page dimensions in points
+    fn page_points(&self) -> (f64, f64) {
+        match self.page_size {
+            Some((w, h)) => (w * PT_PER_MM, h * PT_PER_MM),
+            None => (
+                self.frame.w as f64 * PT_PER_PX,
+                self.frame.h as f64 * PT_PER_PX,
+            ),
+        }
+    }
+
+    /// Drawing operators for all tiles.
+    /// The frame is scaled to cover the page and centered, the overflow is clipped.
+    fn pdf_content(&self, (pw, ph): (f64, f64)) -> String {
+        let (fw, fh) = (self.frame.w as f64, self.frame.h as f64);
+        let scale = (pw / fw).max(ph / fh);
+        let dx = (pw - fw * scale) / 2. - self.frame.x as f64 * scale;
+        let dy = (ph - fh * scale) / 2. - self.frame.y as f64 * scale;
+        let mut s = String::new();
+        // Clip to page, then flip the Y axis since SVG coordinates grow downwards
+        let _ = writeln!(s, "0 0 {:.3} {:.3} re W n", pw, ph);
+        let _ = writeln!(
+            s,
+            "{:.6} 0 0 {:.6} {:.3} {:.3} cm",
+            scale,
+            -scale,
+            dx,
+            ph - dy
+        );
+        // Miter joins, beveled past the same limit as SVG and the rasterizer
+        let _ = writeln!(s, "0 j {} M", MITER_LIMIT);
+        let (mut fill, mut stroke, mut width) = (None, None, None);
+        for p in self.items.iter() {
+            if p.data.0.is_empty() {
+                continue;
+            }
+            if fill != Some(p.fill_color) {
+                let _ = writeln!(s, "{} rg", pdf_color(p.fill_color));
+                fill = Some(p.fill_color);
+            }
+            let stroked = p.stroke_width > 0.;
+            if stroked {
+                if stroke != Some(p.stroke_color) {
+                    let _ = writeln!(s, "{} RG", pdf_color(p.stroke_color));
+                    stroke = Some(p.stroke_color);
+                }
+                if width != Some(p.stroke_width) {
+                    let _ = writeln!(s, "{} w", p.stroke_width);
+                    width = Some(p.stroke_width);
+                }
+            }
+            let Pos(x, y) = p.data.0[0].end();
+            let _ = write!(s, "{:.3} {:.3} m", x, y);
+            let mut from = Pos(x, y);
+            for seg in p.data.0.iter().skip(1) {
+                match *seg {
+                    Segment::Line(Pos(x, y)) => {
+                        let _ = write!(s, " {:.3} {:.3} l", x, y);
+                    }
+                    Segment::Arc(c, to) => {
+                        for [c1, c2, end] in arc_beziers(c, from, to) {
+                            let _ = write!(
+                                s,
+                                " {:.3} {:.3} {:.3} {:.3} {:.3} {:.3} c",
+                                c1.0, c1.1, c2.0, c2.1, end.0, end.1
+                            );
+                        }
+                    }
+                }
+                from = seg.end();
+            }
+            let _ = writeln!(s, " h {}", if stroked { "B" } else { "f" });
+        }
+        s
+    }
+
+    /// Serialize as a single page vector PDF
+    pub fn write_pdf<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (pw, ph) = self.page_points();
+        let content = self.pdf_content((pw, ph));
+        let objects = [
+            String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+            String::from("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Contents 4 0 R /Resources << >> >>",
+                pw, ph
+            ),
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ),
+        ];
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(buf.len());
+            write!(buf, "{} 0 obj\n{}\nendobj\n", i + 1, obj)?;
+        }
+        let xref = buf.len();
+        write!(buf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1)?;
+        for o in offsets {
+            writeln!(buf, "{:010} 00000 n ", o)?;
+        }
+        write!(
+            buf,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )?;
+        out.write_all(&buf)
+    }
+}
+
+/// Cubic Bézier pieces (control points and end) of the short arc around `c`,
+/// one per quarter turn at most
+fn arc_beziers(c: Pos, from: Pos, to: Pos) -> Vec<[Pos; 3]> {
+    let (a0, sweep, r) = arc_angles(c, from, to);
+    let n = (sweep.abs() / (PI / 2.)).ceil().max(1.) as usize;
+    let step = sweep / n as f64;
+    let k = 4. / 3. * (step / 4.).tan() * r;
+    let at = |a: f64| c + (r * a.cos(), r * a.sin());
+    let tangent = |a: f64| Pos(-a.sin(), a.cos()) * k;
+    (0..n)
+        .map(|i| {
+            let (a, b) = (a0 + step * i as f64, a0 + step * (i + 1) as f64);
+            let end = if i + 1 == n { to } else { at(b) };
+            [at(a) + tangent(a), at(b) - tangent(b), end]
+        })
+        .collect()
+}
+
+/// PDF color format: `<r> <g> <b>` within [0; 1]
+fn pdf_color(c: Color) -> String {
+    let [r, g, b] = c.to_rgb();
+    format!(
+        "{:.4} {:.4} {:.4}",
+        r as f64 / 255.,
+        g as f64 / 255.,
+        b as f64 / 255.
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number_after(s: &str, key: &str) -> usize {
+        let rest = &s[s.find(key).unwrap() + key.len()..];
+        let digits = rest
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next();
+        digits.unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn pdf_structure_and_page_size() {
+        let frame = Frame {
+            x: 0,
+            y: 0,
+            w: 300,
+            h: 200,
+            seamless: false,
+        };
+        let mut doc = Document::new(frame).with_page_size(Some((210., 297.)));
+        let arc = Data::new(Pos(10., 10.))
+            .with_line_to(Pos(90., 10.))
+            .with_arc_to(Pos(90., 50.), Pos(90., 90.));
+        doc.add(Path::new(arc).with_stroke_width(1.));
+        let mut buf = Vec::new();
+        doc.write_pdf(&mut buf).unwrap();
+        let pdf = String::from_utf8(buf).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // Cross-reference table points at each object
+        let xref = number_after(&pdf, "startxref");
+        assert!(pdf[xref..].starts_with("xref\n0 5\n"));
+        let entries = pdf[xref..].lines().skip(3).take(4);
+        for (i, line) in entries.enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+        // Stream length matches its content
+        let len = number_after(&pdf, "/Length");
+        let start = pdf.find("stream\n").unwrap() + "stream\n".len();
+        assert!(pdf[start + len..].starts_with("endstream"));
+        assert!(pdf[start..start + len].contains(" c"));
+        assert!(pdf[start..start + len].contains("0 j 4 M\n"));
+        // A4 in points
+        assert!(pdf.contains("/MediaBox [0 0 595.276 841.890]"));
+    }
+}
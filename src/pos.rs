@@ -21,6 +21,17 @@ impl Pos {
         )
     }
 
+    /// Pseudo-random value tied to the position (to the nearest 100'th of unit) and a seed
+    pub fn seeded_hash(self, seed: u64) -> u64 {
+        let (x, y) = self.round();
+        // splitmix64 finalizer
+        let mut z = (seed ^ (u64::from(x as u32) << 32 | u64::from(y as u32)))
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
     pub fn norm(self) -> f64 {
         self.dot_self().sqrt()
     }
@@ -91,6 +102,50 @@ pub fn crossprod_sign(a: Pos, b: Pos, c: Pos) -> bool {
     (a.0 - c.0) * (b.1 - c.1) - (b.0 - c.0) * (a.1 - c.1) > 0.
 }
 
+/// Center of mass of a polygon (mean of the vertices if it is degenerate)
+pub fn centroid(pts: &[Pos]) -> Pos {
+    let (mut area, mut c) = (0., Pos::zero());
+    for i in 0..pts.len() {
+        let (a, b) = (pts[i], pts[(i + 1) % pts.len()]);
+        let cross = a.0 * b.1 - b.0 * a.1;
+        area += cross;
+        c = c + (a + b) * cross;
+    }
+    if area.abs() < 1e-9 {
+        pts.iter().fold(Pos::zero(), |acc, p| acc + *p) * (1. / pts.len().max(1) as f64)
+    } else {
+        c * (1. / (3. * area))
+    }
+}
+
+/// Top-left and bottom-right corners of the smallest box around the points
+pub fn bounding_box(pts: &[Pos]) -> (Pos, Pos) {
+    pts.iter().fold(
+        (
+            Pos(f64::INFINITY, f64::INFINITY),
+            Pos(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        ),
+        |(lo, hi), p| {
+            (
+                Pos(lo.0.min(p.0), lo.1.min(p.1)),
+                Pos(hi.0.max(p.0), hi.1.max(p.1)),
+            )
+        },
+    )
+}
+
+/// Even-odd rule: count the sides crossed by a ray going right from `p`
+pub fn polygon_contains(polygon: &[Pos], p: Pos) -> bool {
+    let mut odd = false;
+    for (k, &a) in polygon.iter().enumerate() {
+        let b = polygon[(k + 1) % polygon.len()];
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            odd = !odd;
+        }
+    }
+    odd
+}
+
 pub fn radians(a: isize) -> f64 {
     (a as f64) * PI / 180.
 }
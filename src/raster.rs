@@ -0,0 +1,384 @@
+use crate::prelude::*;
+use crate::svg::*;
+use std::io;
+
+/// Which rasterizer produces PNG output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Serialize to SVG and let usvg/resvg render it (supports filter effects)
+    #[default]
+    Svg,
+    /// Fill the polygons directly into a pixel buffer
+    Native,
+}
+
+/// An RGBA pixel buffer (premultiplied alpha) with anti-aliased polygon filling.
+///
+/// Coverage is computed exactly from the signed area swept by each edge on each pixel,
+/// accumulated row by row. Only the bounding box of the shape being drawn is visited.
+pub struct Canvas {
+    pub w: usize,
+    pub h: usize,
+    pixels: Vec<u8>,
+    acc: Vec<f64>,
+}
+
+impl Canvas {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            pixels: vec![0; w * h * 4],
+            acc: Vec::new(),
+        }
+    }
+
+    /// Fill a closed polygon
+    pub fn fill(&mut self, pts: &[Pos], c: Color) {
+        self.paint(&[pts], c);
+    }
+
+    /// Outline a closed polygon: every side becomes a rectangle of the given width,
+    /// corners are closed by miter joins, beveled past `MITER_LIMIT` as in SVG.
+    pub fn stroke(&mut self, pts: &[Pos], width: f64, c: Color) {
+        if width <= 0. || pts.len() < 2 {
+            return;
+        }
+        let half = width / 2.;
+        let sides = (0..pts.len())
+            .map(|i| (pts[i], pts[(i + 1) % pts.len()]))
+            .filter(|(a, b)| a.dist(*b) >= f64::EPSILON)
+            .map(|(a, b)| (a, b, (b - a).unit()))
+            .collect::<Vec<_>>();
+        let left = |u: Pos| Pos(-u.1, u.0) * half;
+        let mut polys = Vec::new();
+        for (k, &(a, b, u)) in sides.iter().enumerate() {
+            let n = left(u);
+            let quad = vec![a + n, b + n, b - n, a - n];
+            // Join with the next side, on the outer side of the turn
+            let v = sides[(k + 1) % sides.len()].2;
+            let turn = u.0 * v.1 - u.1 * v.0;
+            if turn.abs() > 1e-9 {
+                let s = -turn.signum();
+                let (p, q) = (b + left(u) * s, b + left(v) * s);
+                let mid = (p + q) * 0.5 - b;
+                let cos = mid.norm() / half;
+                let mut join = if cos * MITER_LIMIT >= 1. {
+                    vec![b, p, b + mid * (1. / (cos * cos)), q]
+                } else {
+                    vec![b, p, q]
+                };
+                // Same winding as the sides, or the union would cancel out
+                if signed_area(&join) * signed_area(&quad) < 0. {
+                    join.reverse();
+                }
+                polys.push(join);
+            }
+            polys.push(quad);
+        }
+        let polys = polys.iter().map(|q| &q[..]).collect::<Vec<_>>();
+        self.paint(&polys, c);
+    }
+
+    /// Fill and outline a path, `origin` of the frame landing on the corner of the canvas
+    /// and lengths multiplied by `scale`
+    pub fn draw(&mut self, path: &Path, origin: Pos, scale: f64) {
+        let pts = path
+            .data
+            .flatten()
+            .into_iter()
+            .map(|q| (q - origin) * scale)
+            .collect::<Vec<_>>();
+        self.fill(&pts, path.fill_color);
+        self.stroke(&pts, path.stroke_width * scale, path.stroke_color);
+    }
+
+    /// Union of polygons composited in a single pass, so that overlaps are not blended twice
+    fn paint(&mut self, polys: &[&[Pos]], c: Color) {
+        let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for p in polys.iter().flat_map(|p| p.iter()) {
+            x0 = x0.min(p.0);
+            y0 = y0.min(p.1);
+            x1 = x1.max(p.0);
+            y1 = y1.max(p.1);
+        }
+        let bx0 = x0.floor().max(0.) as usize;
+        let by0 = y0.floor().max(0.) as usize;
+        let bx1 = (x1.ceil().max(0.) as usize).min(self.w);
+        let by1 = (y1.ceil().max(0.) as usize).min(self.h);
+        if bx0 >= bx1 || by0 >= by1 {
+            return;
+        }
+        let (w, h) = (bx1 - bx0, by1 - by0);
+        let stride = w + 2;
+        self.acc.clear();
+        self.acc.resize(stride * h, 0.);
+        let origin = Pos(bx0 as f64, by0 as f64);
+        for poly in polys {
+            for i in 0..poly.len() {
+                let a = poly[i] - origin;
+                let b = poly[(i + 1) % poly.len()] - origin;
+                self.clipped_line(a, b, w as f64, stride, h);
+            }
+        }
+        let [r, g, b] = c.to_rgb();
+        for y in 0..h {
+            let mut cov = 0.;
+            let row = ((by0 + y) * self.w + bx0) * 4;
+            for x in 0..w {
+                cov += self.acc[y * stride + x];
+                let alpha = cov.abs().min(1.);
+                if alpha <= 0. {
+                    continue;
+                }
+                let px = &mut self.pixels[row + x * 4..row + x * 4 + 4];
+                let inv = 1. - alpha;
+                px[0] = (r as f64 * alpha + px[0] as f64 * inv).round() as u8;
+                px[1] = (g as f64 * alpha + px[1] as f64 * inv).round() as u8;
+                px[2] = (b as f64 * alpha + px[2] as f64 * inv).round() as u8;
+                px[3] = (255. * alpha + px[3] as f64 * inv).round() as u8;
+            }
+        }
+    }
+
+    /// Split an edge where it leaves the box horizontally.
+    /// Outside parts only matter through their winding, so they are flattened on the border.
+    fn clipped_line(&mut self, a: Pos, b: Pos, w: f64, stride: usize, h: usize) {
+        let mut cuts = vec![0., 1.];
+        for bound in [0., w] {
+            let t = (bound - a.0) / (b.0 - a.0);
+            if t > 0. && t < 1. {
+                cuts.push(t);
+            }
+        }
+        cuts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        for i in 0..cuts.len() - 1 {
+            let p = a + (b - a) * cuts[i];
+            let q = a + (b - a) * cuts[i + 1];
+            self.line(
+                Pos(p.0.clamp(0., w), p.1),
+                Pos(q.0.clamp(0., w), q.1),
+                stride,
+                h,
+            );
+        }
+    }
+
+    /// Accumulate the signed area covered by an edge, in box-local coordinates
+    fn line(&mut self, p0: Pos, p1: Pos, stride: usize, h: usize) {
+        if p0.1 == p1.1 {
+            return;
+        }
+        let (dir, p0, p1) = if p0.1 < p1.1 {
+            (1., p0, p1)
+        } else {
+            (-1., p1, p0)
+        };
+        let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+        let mut x = p0.0;
+        if p0.1 < 0. {
+            x -= p0.1 * dxdy;
+        }
+        let ystart = p0.1.max(0.) as usize;
+        let yend = (p1.1.ceil().max(0.) as usize).min(h);
+        for y in ystart..yend {
+            let line = y * stride;
+            let dy = ((y + 1) as f64).min(p1.1) - (y as f64).max(p0.1);
+            // The last row ends on the endpoint itself, rounding could step out of the box
+            let xnext = if (y + 1) as f64 >= p1.1 {
+                p1.0
+            } else {
+                x + dxdy * dy
+            };
+            let d = dy * dir;
+            let (x0, x1) = if x < xnext { (x, xnext) } else { (xnext, x) };
+            let x0floor = x0.floor();
+            let x0i = x0floor as usize;
+            let x1ceil = x1.ceil();
+            let x1i = x1ceil as usize;
+            if x1i <= x0i + 1 {
+                // Edge stays within a single pixel column on this row
+                let xmf = 0.5 * (x + xnext) - x0floor;
+                self.acc[line + x0i] += d - d * xmf;
+                self.acc[line + x0i + 1] += d * xmf;
+            } else {
+                let s = 1. / (x1 - x0);
+                let x0f = x0 - x0floor;
+                let a0 = 0.5 * s * (1. - x0f) * (1. - x0f);
+                let x1f = x1 - x1ceil + 1.;
+                let am = 0.5 * s * x1f * x1f;
+                self.acc[line + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.acc[line + x0i + 1] += d * (1. - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.acc[line + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.acc[line + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f64 * s;
+                    self.acc[line + x1i - 1] += d * (1. - a2 - am);
+                }
+                self.acc[line + x1i] += d * am;
+            }
+            x = xnext;
+        }
+    }
+
+    /// Straight (non premultiplied) RGBA bytes
+    pub fn into_rgba(mut self) -> Vec<u8> {
+        for px in self.pixels.chunks_exact_mut(4) {
+            let a = px[3] as f64;
+            if a > 0. && a < 255. {
+                for c in px.iter_mut().take(3) {
+                    *c = (*c as f64 * 255. / a).round().min(255.) as u8;
+                }
+            }
+        }
+        self.pixels
+    }
+}
+
+/// Longest miter, relative to half the stroke width (SVG default)
+pub(crate) const MITER_LIMIT: f64 = 4.;
+
+fn signed_area(pts: &[Pos]) -> f64 {
+    (0..pts.len())
+        .map(|i| {
+            let (a, b) = (pts[i], pts[(i + 1) % pts.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.
+}
+
+impl Document {
+    /// Render without going through SVG. Filter effects are not supported.
+    pub fn rasterize(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.frame.w, self.frame.h);
+        let origin = Pos(self.frame.x as f64, self.frame.y as f64);
+        for p in self.items.iter() {
+            canvas.draw(p, origin, 1.);
+        }
+        canvas
+    }
+
+    pub(crate) fn save_native(&self, dest: &str) -> io::Result<()> {
+        let canvas = self.rasterize();
+        let (w, h) = (canvas.w as u32, canvas.h as u32);
+        image::save_buffer_with_format(
+            dest,
+            &canvas.into_rgba(),
+            w,
+            h,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|_| io::Error::new(io::ErrorKind::AddrNotAvailable, "Could not save image"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame {
+            x: 0,
+            y: 0,
+            w: 120,
+            h: 80,
+            seamless: false,
+        }
+    }
+
+    /// Fixed documents: plain polygons, strokes, and arcs
+    fn documents() -> Vec<Document> {
+        let mut docs = Vec::new();
+        let mut doc = Document::new(frame());
+        doc.add(
+            Path::new(Data::polygon(vec![
+                Pos(0., 0.),
+                Pos(120., 0.),
+                Pos(120., 80.),
+                Pos(0., 80.),
+            ]))
+            .with_fill_color(Color(240, 230, 210)),
+        );
+        doc.add(
+            Path::new(Data::polygon(vec![
+                Pos(10.3, 5.7),
+                Pos(100.2, 20.1),
+                Pos(40.6, 75.4),
+            ]))
+            .with_fill_color(Color(200, 40, 60)),
+        );
+        docs.push(doc);
+
+        let mut doc = Document::new(frame());
+        for i in 0..6 {
+            for j in 0..4 {
+                let c = Pos(i as f64 * 22. + 7.5, j as f64 * 22. + (i % 2) as f64 * 11.);
+                let hex = (0..6)
+                    .map(|k| c + Pos::polar(60 * k, 12.7))
+                    .collect::<Vec<_>>();
+                doc.add(
+                    Path::new(Data::polygon(hex))
+                        .with_fill_color(Color(40 * i, 60 * j, 150))
+                        .with_stroke_color(Color(0, 0, 0))
+                        .with_stroke_width(1.5),
+                );
+            }
+        }
+        docs.push(doc);
+
+        let mut doc = Document::new(frame());
+        let arc = Data::new(Pos(20., 10.))
+            .with_line_to(Pos(60., 10.))
+            .with_arc_to(Pos(60., 40.), Pos(60., 70.))
+            .with_line_to(Pos(20., 70.));
+        doc.add(
+            Path::new(arc)
+                .with_fill_color(Color(30, 120, 90))
+                .with_stroke_color(Color(250, 250, 250))
+                .with_stroke_width(2.),
+        );
+        docs.push(doc);
+        docs
+    }
+
+    /// Largest difference on a channel of a pixel, out of 255
+    const MAX_PIXEL_DIFF: usize = 48;
+    /// Largest difference on a channel averaged over the image
+    const MAX_MEAN_DIFF: f64 = 2.;
+
+    /// Channel of an RGBA pixel composited over white
+    fn on_white(px: &[u8], k: usize) -> usize {
+        let a = px[3] as usize;
+        (px[k] as usize * a + 255 * (255 - a) + 127) / 255
+    }
+
+    #[test]
+    fn native_matches_resvg() {
+        for doc in documents() {
+            let native = doc.rasterize().into_rgba();
+            let svg = render_svg(&format!("{}", doc)).unwrap();
+            assert_eq!(native.len(), svg.data().len());
+            let diffs = native
+                .chunks(4)
+                .zip(svg.data().chunks(4))
+                .map(|(a, b)| {
+                    (0..3)
+                        .map(|k| on_white(a, k).abs_diff(on_white(b, k)))
+                        .max()
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let mean = diffs.iter().sum::<usize>() as f64 / diffs.len() as f64;
+            let max = *diffs.iter().max().unwrap();
+            // Only anti-aliased edges may differ, and not by much
+            assert!(mean < MAX_MEAN_DIFF, "mean difference {}", mean);
+            assert!(max < MAX_PIXEL_DIFF, "pixel difference {}", max);
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use crate::cfg::SceneCfg;
 use crate::pos::crossprod_sign;
 use crate::prelude::*;
+use crate::svg::Palette;
 use rand::{rngs::StdRng, Rng};
 use std::rc::Rc;
 
 pub struct Scene {
     pub bg: ColorItem,
     pub items: Vec<Rc<dyn Contains>>,
+    pub frame: Frame,
 }
 
 impl Scene {
@@ -14,23 +16,86 @@ impl Scene {
         Self {
             bg: cfg.choose_color(rng),
             items: cfg.create_items(rng),
+            frame: cfg.frame,
         }
     }
 
+    /// Whether an item covers a point. On a seamless frame the items are
+    /// laid out on a torus, so copies shifted by the frame size count as well.
+    fn hits(&self, item: &dyn Contains, p: Pos) -> bool {
+        if !self.frame.seamless {
+            return item.hit(p);
+        }
+        let p = self.frame.wrap(p);
+        let (w, h) = (self.frame.w as f64, self.frame.h as f64);
+        (-1..=1).any(|i| (-1..=1).any(|j| item.hit(p + (i as f64 * w, j as f64 * h))))
+    }
+
     /// Get color of a position depending on objects that were hit
     pub fn color(&self, p: Pos, rng: &mut StdRng) -> Color {
         for i in &self.items {
-            if let Some(c) = i.contains(p, rng) {
-                return c;
+            if self.hits(i.as_ref(), p) {
+                return i.color_item().sample(rng);
             }
         }
         self.bg.sample(rng)
     }
+
+    /// Same as `color`, but also tell which palette entry the color derives from.
+    /// Index 0 is the background, index `i + 1` is `items[i]`.
+    pub fn tint(&self, p: Pos, rng: &mut StdRng) -> (Color, Tint) {
+        for (idx, i) in self.items.iter().enumerate() {
+            if self.hits(i.as_ref(), p) {
+                return i.color_item().sample_tint(idx + 1, rng);
+            }
+        }
+        self.bg.sample_tint(0, rng)
+    }
+
+    /// Index of the item that colors a position, numbered as in `tint`
+    pub fn item_at(&self, p: Pos) -> usize {
+        self.items
+            .iter()
+            .position(|i| self.hits(i.as_ref(), p))
+            .map_or(0, |idx| idx + 1)
+    }
+
+    /// Theme colors the items are drawn from, and the base color of every item
+    /// relative to its theme color, in the order used by `tint`
+    pub fn palette(&self) -> Palette {
+        let mut palette = Palette::default();
+        let colors = std::iter::once(&self.bg).chain(self.items.iter().map(|i| i.color_item()));
+        for item in colors {
+            let theme = match palette.themes.iter().position(|&t| t == item.theme) {
+                Some(k) => k,
+                None => {
+                    palette.themes.push(item.theme);
+                    palette.themes.len() - 1
+                }
+            };
+            palette
+                .items
+                .push((theme, item.base().offset_from(item.theme)));
+        }
+        palette
+    }
 }
 
 /// Trait for anything that can contain a 2D point
 pub trait Contains: std::fmt::Display {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color>;
+    /// Check whether the point lies inside the item
+    fn hit(&self, p: Pos) -> bool;
+
+    /// Color information attached to the item
+    fn color_item(&self) -> &ColorItem;
+
+    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+        if self.hit(p) {
+            Some(self.color_item().sample(rng))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,11 +109,39 @@ pub struct ColorItem {
 
 impl ColorItem {
     pub fn sample(&self, rng: &mut StdRng) -> Color {
-        self.salt.sample(rng).unwrap_or_else(|| {
-            self.shade
-                .meanpoint(self.theme, self.distance)
-                .variate(rng, self.deviation)
-        })
+        self.salt
+            .sample(rng)
+            .unwrap_or_else(|| self.base().variate(rng, self.deviation))
+    }
+
+    /// Color before any random variation is applied
+    pub fn base(&self) -> Color {
+        self.shade.meanpoint(self.theme, self.distance)
+    }
+
+    /// Sample a color and express it relative to the base color.
+    /// Salt is not part of the theme, so it yields no offset.
+    pub fn sample_tint(&self, index: usize, rng: &mut StdRng) -> (Color, Tint) {
+        match self.salt.sample(rng) {
+            Some(c) => (
+                c,
+                Tint {
+                    index,
+                    offset: None,
+                },
+            ),
+            None => {
+                let base = self.base();
+                let c = base.variate(rng, self.deviation);
+                (
+                    c,
+                    Tint {
+                        index,
+                        offset: Some(c.offset_from(base)),
+                    },
+                )
+            }
+        }
     }
 }
 
@@ -72,12 +165,12 @@ impl Disc {
 }
 
 impl Contains for Disc {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
-        if (self.center - p).dot_self() < self.radius.powi(2) {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+    fn hit(&self, p: Pos) -> bool {
+        (self.center - p).dot_self() < self.radius.powi(2)
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -109,13 +202,13 @@ impl HalfPlane {
 }
 
 impl Contains for HalfPlane {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let dotprod = (p - self.limit).dot(self.reference - self.limit);
-        if dotprod < 0. {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+        dotprod < 0.
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -142,17 +235,17 @@ impl Triangle {
 }
 
 impl Contains for Triangle {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let d1 = crossprod_sign(p, self.a, self.b);
         let d2 = crossprod_sign(p, self.b, self.c);
         let d3 = crossprod_sign(p, self.c, self.a);
         let has_pos = d1 || d2 || d3;
         let has_neg = !(d1 && d2 && d3);
-        if !(has_neg && has_pos) {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+        !(has_neg && has_pos)
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -192,15 +285,15 @@ impl FracPart for f64 {
 }
 
 impl Contains for Spiral {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let Pos(di, dj) = self.center - p;
         let theta = di.atan2(dj);
         let radius = (di.powi(2) + dj.powi(2)).sqrt() + theta / std::f64::consts::PI * self.width;
-        if (radius / self.width).frac_part() < self.tightness {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+        (radius / self.width).frac_part() < self.tightness
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -224,14 +317,14 @@ impl Stripe {
 }
 
 impl Contains for Stripe {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let dotprod1 = (p - self.limit).dot(self.reference - self.limit);
         let dotprod2 = (p - self.reference).dot(self.limit - self.reference);
-        if dotprod1 > 0. && dotprod2 > 0. {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+        dotprod1 > 0. && dotprod2 > 0.
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -264,16 +357,15 @@ impl Wave {
 }
 
 impl Contains for Wave {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let proj = (p - self.limit).project(self.reference - self.limit);
         let nearpt = p - proj;
         let phase = (self.limit - nearpt).norm() * self.frequency;
-        if phase.cos() * self.amplitude > (p - self.limit).dot((self.reference - self.limit).unit())
-        {
-            Some(self.color.sample(rng))
-        } else {
-            None
-        }
+        phase.cos() * self.amplitude > (p - self.limit).dot((self.reference - self.limit).unit())
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
     }
 }
 
@@ -306,7 +398,7 @@ impl Sawtooth {
 }
 
 impl Contains for Sawtooth {
-    fn contains(&self, p: Pos, rng: &mut StdRng) -> Option<Color> {
+    fn hit(&self, p: Pos) -> bool {
         let sawtooth = |f: f64| {
             let int = f.floor();
             let frac = f - int;
@@ -321,12 +413,64 @@ impl Contains for Sawtooth {
         let proj = (p - self.limit).project(self.reference - self.limit);
         let nearpt = p - proj;
         let phase = (self.limit - nearpt).norm() * self.frequency;
-        if sawtooth(phase) * self.amplitude
+        sawtooth(phase) * self.amplitude
             > (p - self.limit).dot((self.reference - self.limit).unit())
-        {
-            Some(self.color.sample(rng))
-        } else {
-            None
+    }
+
+    fn color_item(&self) -> &ColorItem {
+        &self.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(shade: Color, theme: Color) -> ColorItem {
+        ColorItem {
+            shade,
+            deviation: 0,
+            theme,
+            distance: 40,
+            salt: Salt::none(),
+        }
+    }
+
+    #[test]
+    fn items_of_a_theme_color_share_it() {
+        let (dark, light) = (Color(20, 30, 40), Color(220, 210, 200));
+        let disc = |shade| -> Rc<dyn Contains> {
+            Rc::new(Disc {
+                center: Pos(0., 0.),
+                radius: 1.,
+                color: item(shade, light),
+            })
+        };
+        let scene = Scene {
+            bg: item(Color(0, 0, 0), dark),
+            items: vec![disc(Color(255, 0, 0)), disc(Color(0, 0, 255))],
+            frame: Frame {
+                x: 0,
+                y: 0,
+                w: 10,
+                h: 10,
+                seamless: false,
+            },
+        };
+        let palette = scene.palette();
+        assert_eq!(palette.themes, vec![dark, light]);
+        let themes: Vec<_> = palette.items.iter().map(|(k, _)| *k).collect();
+        assert_eq!(themes, vec![0, 1, 1]);
+        // Each item keeps its own base color
+        for (i, (k, [dr, dg, db])) in palette.items.iter().enumerate() {
+            let t = palette.themes[*k];
+            let base = if i == 0 {
+                scene.bg.base()
+            } else {
+                scene.items[i - 1].color_item().base()
+            };
+            let add = |c: usize, d: isize| (c as isize + d) as usize;
+            assert_eq!(base, Color(add(t.0, *dr), add(t.1, *dg), add(t.2, *db)));
         }
     }
 }
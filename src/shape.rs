@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use crate::svg::*;
+use std::f64::consts::PI;
 
 /// A generic shape that can be placed at any position according to a given center
 pub struct Movable(Vec<Pos>);
@@ -37,6 +38,24 @@ impl Movable {
         Movable(pts)
     }
 
+    /// Regular polygon with `n` sides of length `side`, the first one facing the direction `rot`
+    pub fn regular(n: usize, side: f64, rot: isize) -> Self {
+        let r = side / (2. * (PI / n as f64).sin());
+        let pts = (0..n)
+            .map(|k| {
+                let a = radians(rot) + PI * (2 * k + 1) as f64 / n as f64;
+                Pos(r * a.cos(), r * a.sin())
+            })
+            .collect();
+        Movable(pts)
+    }
+
+    /// Rectangle with sides `w` along the direction `rot` and `h` across it
+    pub fn rectangle(w: f64, h: f64, rot: isize) -> Self {
+        let (a, b) = (Pos::polar(rot, w / 2.), Pos::polar(rot + 90, h / 2.));
+        Movable(vec![a + b, b - a, -a - b, a - b])
+    }
+
     pub fn rhombus(ldiag: f64, sdiag: f64, rot: isize) -> Self {
         Movable(vec![
             Pos::polar(rot, ldiag),
@@ -46,10 +65,76 @@ impl Movable {
         ])
     }
 
+    /// Pentagon with the given angles (in degrees), built by walking the first three sides
+    /// from vertex 0 along the x axis, then centered on its vertices
+    pub fn pentagon(angles: [f64; 5], sizes: [f64; 3]) -> Self {
+        Self::polygon(&angles, &sizes).unwrap()
+    }
+
+    /// Polygon with the given angles (in degrees), built by walking the given sides
+    /// from vertex 0 along the x axis, then centered on its vertices.
+    /// With one side less than the number of angles the last side is simply closed,
+    /// with two sides less the last two sides meet where the angles require.
+    pub fn polygon(angles: &[f64], sizes: &[f64]) -> Option<Self> {
+        let n = angles.len();
+        if n < 3 || sizes.len() + 2 < n || sizes.len() >= n {
+            return None;
+        }
+        let dir = |a: f64| Pos(a.to_radians().cos(), a.to_radians().sin());
+        let mut pts = vec![Pos::zero()];
+        let mut running_angle = 0.;
+        for (i, size) in sizes.iter().enumerate() {
+            let latest = pts[i];
+            pts.push(latest + dir(running_angle) * *size);
+            running_angle += 180. - angles[i + 1];
+        }
+        if sizes.len() + 2 == n {
+            // Last vertex is where the next to last side meets the last one
+            let last = pts[n - 2];
+            let (u, v) = (dir(running_angle), dir(angles[0]));
+            let cross = v.0 * u.1 - v.1 * u.0;
+            if cross.abs() < 1e-9 {
+                return None;
+            }
+            let t = (last.0 * v.1 - last.1 * v.0) / cross;
+            pts.push(last + u * t);
+        }
+        let mid = pts.iter().fold(Pos::zero(), |acc, item| acc + *item) * (1. / n as f64);
+        Some(Movable(pts.into_iter().map(|p| p - mid).collect()))
+    }
+
+    /// Same shape turned by `rot` degrees around its reference
+    pub fn rotated(&self, rot: f64) -> Self {
+        let (c, s) = (rot.to_radians().cos(), rot.to_radians().sin());
+        Movable(
+            self.0
+                .iter()
+                .map(|p| Pos(p.0 * c - p.1 * s, p.0 * s + p.1 * c))
+                .collect(),
+        )
+    }
+
+    /// Mirror image, with the vertices still in counterclockwise order
+    pub fn mirrored(&self) -> Self {
+        Movable(self.0.iter().rev().map(|p| Pos(p.0, -p.1)).collect())
+    }
+
+    /// Turn `other` so that it can be glued to `self` with its vertex `b` on vertex `a`,
+    /// the side that starts at `b` running along the one that ends at `a`
+    pub fn glue(&self, a: usize, other: &Self, b: usize) -> Self {
+        let (u, v) = (-self.side(a + self.0.len() - 1), other.side(b));
+        let rot = u.1.atan2(u.0) - v.1.atan2(v.0);
+        other.rotated(rot.to_degrees())
+    }
+
     pub fn from(v: Vec<Pos>) -> Self {
         Self(v)
     }
 
+    pub fn vertices(&self) -> &[Pos] {
+        &self.0
+    }
+
     pub fn vertex(&self, idx: usize) -> Pos {
         self.0[idx % self.0.len()]
     }
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::raster::Backend;
 use std::fmt;
 use std::io::{self, Write};
 
@@ -6,29 +7,237 @@ pub struct Path {
     pub stroke_width: f64,
     pub stroke_color: Color,
     pub fill_color: Color,
+    pub tint: Option<Tint>,
+    /// Prototile type, for tilings made of several distinct shapes
+    pub kind: Option<usize>,
     pub data: Data,
 }
 
-pub struct Data(pub Vec<Pos>);
+/// Closed outline, as a list of segments each ending at a point.
+/// The first segment only sets the starting point.
+pub struct Data(pub Vec<Segment>);
+
+/// Piece of an outline
+#[derive(Clone, Copy, Debug)]
+pub enum Segment {
+    /// Straight line to a point
+    Line(Pos),
+    /// Circular arc to a point around a center (first), the short way round
+    Arc(Pos, Pos),
+}
 
 pub struct Document {
     pub frame: Frame,
     pub items: Vec<Path>,
+    pub palette: Palette,
+    pub themed: bool,
+    pub effects: Effects,
+    /// Physical size in millimeters, for printable formats
+    pub page_size: Option<(f64, f64)>,
+    /// Rasterizer used for PNG output
+    pub backend: Backend,
+}
+
+/// Optional post-processing, expressed as SVG filters.
+/// A value of 0 disables the corresponding effect.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Effects {
+    /// Opacity of the film grain laid over the whole image
+    pub grain: f64,
+    /// Blur radius of the whole composition
+    pub blur: f64,
+    /// Blur radius of the tiles painted by the pattern, background stays sharp
+    pub blur_pattern: f64,
+    /// Radius of the soft shadow inside each tile
+    pub shadow: f64,
+}
+
+/// Colors of themed output. Each theme color becomes a CSS custom property
+/// (`--bg-0`, `--bg-1`, ...), scene items derive their base color from one of them.
+/// The shift of each kind of tile becomes one as well (`--kind-0`, `--kind-1`, ...).
+#[derive(Clone, Debug, Default)]
+pub struct Palette {
+    pub themes: Vec<Color>,
+    /// Theme color of each scene item and the offset of its base color from it,
+    /// in the order of `Tint::index`
+    pub items: Vec<(usize, [isize; 3])>,
+    /// Amount added to every channel of the tiles of each kind
+    pub kinds: Vec<isize>,
+}
+
+/// SVG output where theme colors are CSS custom properties (`--bg-0`, `--bg-1`, ...)
+/// that can be overridden by the embedding page.
+pub struct Themed<'a>(pub &'a Document);
+
+impl Segment {
+    /// Point where the segment ends
+    pub fn end(self) -> Pos {
+        match self {
+            Segment::Line(p) | Segment::Arc(_, p) => p,
+        }
+    }
 }
 
 impl Data {
     pub fn new(pos: Pos) -> Self {
-        Self(vec![pos])
+        Self(vec![Segment::Line(pos)])
+    }
+
+    /// Straight sides only
+    pub fn polygon(pts: Vec<Pos>) -> Self {
+        Self(pts.into_iter().map(Segment::Line).collect())
     }
 
     pub fn line_to(&mut self, pos: Pos) {
-        self.0.push(pos);
+        self.0.push(Segment::Line(pos));
     }
 
     pub fn with_line_to(mut self, pos: Pos) -> Self {
-        self.0.push(pos);
+        self.line_to(pos);
+        self
+    }
+
+    pub fn arc_to(&mut self, center: Pos, pos: Pos) {
+        self.0.push(Segment::Arc(center, pos));
+    }
+
+    pub fn with_arc_to(mut self, center: Pos, pos: Pos) -> Self {
+        self.arc_to(center, pos);
         self
     }
+
+    /// Outline moved point by point, arcs are flattened first
+    pub fn mapped(&self, m: impl Fn(Pos) -> Pos) -> Self {
+        Self::polygon(self.flatten().into_iter().map(m).collect())
+    }
+
+    /// Flattened outline with sides cut in pieces no longer than `step`,
+    /// so that it bends smoothly when mapped
+    pub fn refined(&self, step: f64) -> Self {
+        let pts = self.flatten();
+        let mut out = Vec::with_capacity(pts.len());
+        for (k, &a) in pts.iter().enumerate() {
+            let b = pts[(k + 1) % pts.len()];
+            let n = (a.dist(b) / step).ceil().max(1.) as usize;
+            for i in 0..n {
+                out.push(a + (b - a) * (i as f64 / n as f64));
+            }
+        }
+        Self::polygon(out)
+    }
+
+    /// Endpoints of all segments
+    pub fn vertices(&self) -> Vec<Pos> {
+        self.0.iter().map(|s| s.end()).collect()
+    }
+
+    /// Polygon that follows arcs within a fraction of a pixel
+    pub fn flatten(&self) -> Vec<Pos> {
+        let mut pts: Vec<Pos> = Vec::with_capacity(self.0.len());
+        for seg in &self.0 {
+            match (*seg, pts.last()) {
+                (Segment::Arc(c, to), Some(&from)) => {
+                    let (a0, sweep, r) = arc_angles(c, from, to);
+                    // Chord error r(1 - cos(step/2)) stays below 0.1
+                    let step = 2. * (1. - 0.1 / r.max(0.1)).acos();
+                    let n = (sweep.abs() / step.max(0.01)).ceil().max(1.) as usize;
+                    for k in 1..n {
+                        let a = a0 + sweep * k as f64 / n as f64;
+                        pts.push(c + (r * a.cos(), r * a.sin()));
+                    }
+                    pts.push(to);
+                }
+                _ => pts.push(seg.end()),
+            }
+        }
+        pts
+    }
+}
+
+impl Data {
+    /// Flattened outline without repeated points
+    fn corners(&self) -> Vec<Pos> {
+        let mut pts = self.flatten();
+        pts.dedup_by(|a, b| a.dist(*b) < 1e-6);
+        while pts.len() > 1 && pts[0].dist(pts[pts.len() - 1]) < 1e-6 {
+            pts.pop();
+        }
+        pts
+    }
+
+    /// Outline with every side moved inwards by `d`, none if the shape is too small for it
+    pub fn inset(&self, d: f64) -> Option<Self> {
+        let pts = self.corners();
+        let n = pts.len();
+        if n < 3 {
+            return None;
+        }
+        let area = signed_area(&pts);
+        // Inside is on the left of the sides when the area is positive
+        let inwards = |a: Pos, b: Pos| {
+            let u = (b - a).unit();
+            Pos(-u.1, u.0) * area.signum()
+        };
+        let mut out = Vec::with_capacity(n);
+        for i in 0..n {
+            let (a, v, b) = (pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n]);
+            let (n1, n2) = (inwards(a, v), inwards(v, b));
+            let m = n1 + n2;
+            if m.norm() < 1e-9 {
+                continue;
+            }
+            let m = m.unit();
+            // Miter, limited on very sharp corners
+            out.push(v + m * (d / m.dot(n1).max(0.25)));
+        }
+        let inner = signed_area(&out);
+        if inner * area <= 0. || inner.abs() < 1. {
+            None
+        } else {
+            Some(Self::polygon(out))
+        }
+    }
+
+    /// Outline with corners replaced by arcs of radius `r`,
+    /// smaller where the sides are too short
+    pub fn rounded(&self, r: f64) -> Self {
+        let pts = self.corners();
+        let n = pts.len();
+        let mut data = Self(Vec::with_capacity(2 * n));
+        for i in 0..n {
+            let (a, v, b) = (pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n]);
+            let (u1, u2) = ((a - v).unit(), (b - v).unit());
+            // Half of the angle at the corner
+            let half = u1.dot(u2).clamp(-1., 1.).acos() / 2.;
+            if n < 3 || half > radians(89) {
+                data.0.push(Segment::Line(v));
+                continue;
+            }
+            let t = (r / half.tan()).min(a.dist(v) / 2.).min(b.dist(v) / 2.);
+            let center = v + (u1 + u2).unit() * (t / half.cos());
+            data.0.push(Segment::Line(v + u1 * t));
+            data.0.push(Segment::Arc(center, v + u2 * t));
+        }
+        data
+    }
+}
+
+/// Twice the area of a polygon, positive when the vertices turn left
+fn signed_area(pts: &[Pos]) -> f64 {
+    (0..pts.len())
+        .map(|i| {
+            let (a, b) = (pts[i], pts[(i + 1) % pts.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum()
+}
+
+/// Starting angle, signed angle swept and radius of the short arc around `c` from `from` to `to`
+pub fn arc_angles(c: Pos, from: Pos, to: Pos) -> (f64, f64, f64) {
+    let (u, v) = (from - c, to - c);
+    let a0 = u.1.atan2(u.0);
+    let sweep = (u.0 * v.1 - u.1 * v.0).atan2(u.dot(v));
+    (a0, sweep, u.norm())
 }
 
 impl Path {
@@ -37,6 +246,8 @@ impl Path {
             stroke_width: 0.0,
             stroke_color: Color(0, 0, 0),
             fill_color: Color(255, 255, 255),
+            tint: None,
+            kind: None,
             data: d,
         }
     }
@@ -55,6 +266,16 @@ impl Path {
         self.stroke_width = w;
         self
     }
+
+    pub fn with_tint(mut self, t: Option<Tint>) -> Self {
+        self.tint = t;
+        self
+    }
+
+    pub fn with_kind(mut self, k: usize) -> Self {
+        self.kind = Some(k);
+        self
+    }
 }
 
 impl Document {
@@ -62,9 +283,36 @@ impl Document {
         Self {
             frame,
             items: Vec::new(),
+            palette: Palette::default(),
+            themed: false,
+            effects: Effects::default(),
+            page_size: None,
+            backend: Backend::default(),
         }
     }
 
+    pub fn with_effects(mut self, effects: Effects) -> Self {
+        self.effects = effects;
+        self
+    }
+
+    pub fn with_page_size(mut self, size: Option<(f64, f64)>) -> Self {
+        self.page_size = size;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Switch to themed SVG output, using the given colors as default values
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self.themed = true;
+        self
+    }
+
     pub fn add(&mut self, path: Path) {
         self.items.push(path);
     }
@@ -72,76 +320,241 @@ impl Document {
     pub fn save(&self, dest: &str) -> io::Result<()> {
         if dest.ends_with(".svg") || dest.ends_with(".svg.tmp") {
             let mut buffer = std::fs::File::create(dest)?;
-            buffer.write_all(&format!("{}", &self).into_bytes())
+            if self.themed {
+                buffer.write_all(&format!("{}", Themed(self)).into_bytes())
+            } else {
+                buffer.write_all(&format!("{}", &self).into_bytes())
+            }
         } else if dest.ends_with(".png") || dest.ends_with(".png.tmp") {
-            {
-                // The following code uses functionality from two crates licensed under MPL 2.0
-                //   usvg: https://crates.io/crates/usvg
-                //   resvg: https://crates.io/crates/resvg
-                let svg_data = format!("{}", &self);
-                let tree = match usvg::Tree::from_str(&svg_data, &usvg::Options::default()) {
-                    Ok(tree) => tree,
-                    Err(_) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "Failed to parse svg",
-                        ))
-                    }
-                };
-                let fit_to = usvg::FitTo::Original;
-                let bg = None;
-                let converted = match resvg::render(&tree, fit_to, bg) {
-                    Some(img) => img,
-                    None => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "Failed to convert to png",
-                        ))
-                    }
-                };
+            // Filters can only be rendered by resvg
+            if self.backend == Backend::Native && self.effects.is_empty() {
+                self.save_native(dest)
+            } else {
+                let converted = render_svg(&format!("{}", &self))?;
                 match converted.save_png(dest) {
                     Ok(_) => Ok(()),
-                    Err(_) => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::AddrNotAvailable,
-                            "Could not save image",
-                        ))
-                    }
+                    Err(_) => Err(io::Error::new(
+                        io::ErrorKind::AddrNotAvailable,
+                        "Could not save image",
+                    )),
                 }
             }
+        } else if dest.ends_with(".pdf") || dest.ends_with(".pdf.tmp") {
+            let mut buffer = std::fs::File::create(dest)?;
+            self.write_pdf(&mut buffer)
         } else {
             Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "Can only support .svg and .png extensions",
+                "Can only support .svg, .png and .pdf extensions",
             ))
         }
     }
 }
 
+impl Path {
+    /// Write as an SVG element, optionally referencing the palette and a filter
+    fn write(&self, f: &mut fmt::Formatter, themed: bool, filter: Option<&str>) -> fmt::Result {
+        let edge = self.stroke_color == self.fill_color;
+        write!(f, "<path d=\"{}\"", self.data)?;
+        match self.tint {
+            Some(Tint {
+                index,
+                offset: Some(offset),
+            }) if themed => {
+                write!(
+                    f,
+                    " class=\"tile item-{}{}",
+                    index,
+                    if edge { " tile-edge" } else { "" }
+                )?;
+                if let Some(kind) = self.kind {
+                    write!(f, " kind-{}", kind)?;
+                }
+                write!(f, "\"")?;
+                if offset != [0, 0, 0] {
+                    write!(f, " style=\"")?;
+                    for (name, d) in ["--dr", "--dg", "--db"].iter().zip(offset.iter()) {
+                        if *d != 0 {
+                            write!(f, "{}:{};", name, d)?;
+                        }
+                    }
+                    write!(f, "\"")?;
+                }
+                // Precomputed colors for viewers without relative color syntax
+                write!(
+                    f,
+                    " fill=\"{}\" stroke=\"{}\"",
+                    self.fill_color, self.stroke_color
+                )?;
+            }
+            _ => write!(
+                f,
+                " fill=\"{}\" stroke=\"{}\"",
+                self.fill_color, self.stroke_color
+            )?,
+        }
+        if let Some(id) = filter {
+            write!(f, " filter=\"url(#{})\"", id)?;
+        }
+        write!(f, " stroke-width=\"{}\" />", self.stroke_width)
+    }
+
+    /// Whether the path was painted by a pattern item rather than the background
+    fn in_pattern(&self) -> bool {
+        matches!(self.tint, Some(Tint { index, .. }) if index > 0)
+    }
+}
+
+/// Rasterize SVG source with resvg
+pub(crate) fn render_svg(svg_data: &str) -> io::Result<resvg::Image> {
+    // The following code uses functionality from two crates licensed under MPL 2.0
+    //   usvg: https://crates.io/crates/usvg
+    //   resvg: https://crates.io/crates/resvg
+    let tree = match usvg::Tree::from_str(svg_data, &usvg::Options::default()) {
+        Ok(tree) => tree,
+        Err(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Failed to parse svg",
+            ))
+        }
+    };
+    let fit_to = usvg::FitTo::Original;
+    let bg = None;
+    resvg::render(&tree, fit_to, bg)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Failed to convert to png"))
+}
+
 impl fmt::Display for Path {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "<path d=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\" />",
-            self.data, self.fill_color, self.stroke_color, self.stroke_width
-        )
+        self.write(f, false, None)
     }
 }
 
 impl fmt::Display for Data {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if !self.0.is_empty() {
-            let Pos(x, y) = self.0[0];
+        if let Some(start) = self.0.first() {
+            let Pos(x, y) = start.end();
             write!(f, "M{},{} ", x, y)?;
         }
-        for Pos(x, y) in self.0.iter().skip(1) {
-            write!(f, "L{},{} ", x, y)?;
+        let mut from = Pos::zero();
+        for (i, seg) in self.0.iter().enumerate() {
+            match *seg {
+                _ if i == 0 => (),
+                Segment::Line(Pos(x, y)) => write!(f, "L{},{} ", x, y)?,
+                Segment::Arc(c, to) => {
+                    let (_, sweep, r) = arc_angles(c, from, to);
+                    let Pos(x, y) = to;
+                    write!(f, "A{},{} 0 0,{} {},{} ", r, r, u8::from(sweep > 0.), x, y)?
+                }
+            }
+            from = seg.end();
         }
         write!(f, "z")
     }
 }
 
-impl fmt::Display for Document {
+impl Effects {
+    fn is_empty(&self) -> bool {
+        self.grain <= 0. && self.blur <= 0. && self.blur_pattern <= 0. && self.shadow <= 0.
+    }
+
+    /// Filter definitions referenced by the document
+    fn write_defs(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "<defs>")?;
+        if self.grain > 0. {
+            writeln!(
+                f,
+                "<filter id=\"grain\" x=\"0\" y=\"0\" width=\"1\" height=\"1\">"
+            )?;
+            writeln!(
+                f,
+                "<feTurbulence type=\"fractalNoise\" baseFrequency=\"0.8\" numOctaves=\"3\" stitchTiles=\"stitch\" />"
+            )?;
+            writeln!(
+                f,
+                "<feColorMatrix type=\"matrix\" values=\"1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 0 0 0 0 {}\" />",
+                self.grain.min(1.)
+            )?;
+            writeln!(
+                f,
+                "<feComposite in2=\"SourceAlpha\" operator=\"in\" result=\"noise\" />"
+            )?;
+            writeln!(
+                f,
+                "<feMerge><feMergeNode in=\"SourceGraphic\" /><feMergeNode in=\"noise\" /></feMerge>"
+            )?;
+            writeln!(f, "</filter>")?;
+        }
+        if self.blur > 0. {
+            writeln!(
+                f,
+                "<filter id=\"blur\"><feGaussianBlur stdDeviation=\"{}\" /></filter>",
+                self.blur
+            )?;
+        }
+        if self.blur_pattern > 0. {
+            writeln!(
+                f,
+                "<filter id=\"blur-pattern\"><feGaussianBlur stdDeviation=\"{}\" /></filter>",
+                self.blur_pattern
+            )?;
+        }
+        if self.shadow > 0. {
+            writeln!(
+                f,
+                "<filter id=\"shadow\" x=\"-0.5\" y=\"-0.5\" width=\"2\" height=\"2\">"
+            )?;
+            writeln!(
+                f,
+                "<feFlood flood-color=\"black\" flood-opacity=\"{}\" />",
+                SHADOW_OPACITY
+            )?;
+            writeln!(f, "<feComposite in2=\"SourceAlpha\" operator=\"out\" />")?;
+            writeln!(f, "<feGaussianBlur stdDeviation=\"{}\" />", self.shadow)?;
+            writeln!(
+                f,
+                "<feOffset dx=\"{}\" dy=\"{}\" />",
+                self.shadow / 2.,
+                self.shadow / 2.
+            )?;
+            writeln!(
+                f,
+                "<feComposite in2=\"SourceAlpha\" operator=\"in\" result=\"inner\" />"
+            )?;
+            writeln!(
+                f,
+                "<feMerge><feMergeNode in=\"SourceGraphic\" /><feMergeNode in=\"inner\" /></feMerge>"
+            )?;
+            writeln!(f, "</filter>")?;
+        }
+        writeln!(f, "</defs>")
+    }
+}
+
+const SHADOW_OPACITY: f64 = 0.5;
+
+/// Opening of an SVG file: root element, palette, filters and effect groups
+struct Header<'a> {
+    frame: Frame,
+    palette: Option<&'a Palette>,
+    effects: Effects,
+}
+
+/// Closing of the groups opened by `Header`
+struct Footer(Effects);
+
+/// A single path as written in a document
+struct Element<'a> {
+    path: &'a Path,
+    themed: bool,
+    shadow: bool,
+}
+
+impl fmt::Display for Header<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let (x1, y1, x2, y2) = self.frame.into_tuple();
         let src = String::from("http://www.w3.org/2000/svg");
@@ -150,9 +563,325 @@ impl fmt::Display for Document {
             "<svg viewBox=\"{} {} {} {}\" xmlns=\"{}\">",
             x1, y1, x2, y2, src
         )?;
-        for p in self.items.iter() {
-            writeln!(f, "{}", p)?;
+        if let Some(palette) = self.palette {
+            write_style(f, palette)?;
+        }
+        self.effects.write_defs(f)?;
+        if self.effects.grain > 0. {
+            writeln!(f, "<g filter=\"url(#grain)\">")?;
+        }
+        if self.effects.blur > 0. {
+            writeln!(f, "<g filter=\"url(#blur)\">")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Footer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.blur > 0. {
+            writeln!(f, "</g>")?;
+        }
+        if self.0.grain > 0. {
+            writeln!(f, "</g>")?;
         }
         write!(f, "</svg>")
     }
 }
+
+impl fmt::Display for Element<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let filter = if self.shadow { Some("shadow") } else { None };
+        self.path.write(f, self.themed, filter)
+    }
+}
+
+/// Theme colors as CSS custom properties, and the classes that reference them
+fn write_style(f: &mut fmt::Formatter, palette: &Palette) -> fmt::Result {
+    writeln!(f, "<style>")?;
+    write!(f, "svg {{ ")?;
+    for (i, c) in palette.themes.iter().enumerate() {
+        write!(f, "--bg-{}: {}; ", i, c)?;
+    }
+    for (k, shift) in palette.kinds.iter().enumerate() {
+        write!(f, "--kind-{}: {}; ", k, shift)?;
+    }
+    writeln!(f, "}}")?;
+    // Tiles also carry their colors as attributes, which these rules override
+    // only where relative colors are understood
+    writeln!(f, "@supports (color: rgb(from red r g b)) {{")?;
+    for (i, (theme, [dr, dg, db])) in palette.items.iter().enumerate() {
+        writeln!(
+            f,
+            ".item-{} {{ --base: rgb(from var(--bg-{}) calc(r + {}) calc(g + {}) calc(b + {})); }}",
+            i, theme, dr, dg, db
+        )?;
+    }
+    for k in 0..palette.kinds.len() {
+        writeln!(f, ".kind-{} {{ --kind: var(--kind-{}); }}", k, k)?;
+    }
+    writeln!(
+        f,
+        ".tile {{ --tint: rgb(from var(--base) calc(r + var(--dr, 0) + var(--kind, 0)) calc(g + var(--dg, 0) + var(--kind, 0)) calc(b + var(--db, 0) + var(--kind, 0))); fill: var(--tint); }}"
+    )?;
+    writeln!(f, ".tile-edge {{ stroke: var(--tint); }}")?;
+    writeln!(f, "}}")?;
+    writeln!(f, "</style>")
+}
+
+impl Document {
+    fn write_svg(&self, f: &mut fmt::Formatter, themed: bool) -> fmt::Result {
+        let fx = self.effects;
+        let header = Header {
+            frame: self.frame,
+            palette: if themed { Some(&self.palette) } else { None },
+            effects: fx,
+        };
+        write!(f, "{}", header)?;
+        let element = |path| Element {
+            path,
+            themed,
+            shadow: fx.shadow > 0.,
+        };
+        if fx.blur_pattern > 0. {
+            // Background first so that the blurred pattern bleeds over it
+            for p in self.items.iter().filter(|p| !p.in_pattern()) {
+                writeln!(f, "{}", element(p))?;
+            }
+            writeln!(f, "<g filter=\"url(#blur-pattern)\">")?;
+            for p in self.items.iter().filter(|p| p.in_pattern()) {
+                writeln!(f, "{}", element(p))?;
+            }
+            writeln!(f, "</g>")?;
+        } else {
+            for p in self.items.iter() {
+                writeln!(f, "{}", element(p))?;
+            }
+        }
+        write!(f, "{}", Footer(fx))
+    }
+}
+
+/// Writes an SVG document incrementally, without keeping its paths in memory.
+///
+/// Behaves like `Document` except for `Effects::blur_pattern`, which is ignored:
+/// the blurred pattern must bleed over the background, so every background tile has to be
+/// written before the first pattern one, and paths arrive in tiling order.
+/// Blurring each pattern tile on its own would let the next background tiles cover the bleed.
+/// Callers that need the effect build a `Document` instead, as `stream_svg` does.
+pub struct SvgStream<W: Write> {
+    out: W,
+    themed: bool,
+    effects: Effects,
+}
+
+impl<W: Write> SvgStream<W> {
+    /// Write the header. A palette switches to themed output.
+    pub fn new(
+        mut out: W,
+        frame: Frame,
+        palette: Option<&Palette>,
+        effects: Effects,
+    ) -> io::Result<Self> {
+        let header = Header {
+            frame,
+            palette,
+            effects,
+        };
+        write!(out, "{}", header)?;
+        Ok(Self {
+            out,
+            themed: palette.is_some(),
+            effects,
+        })
+    }
+
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let element = Element {
+            path,
+            themed: self.themed,
+            shadow: self.effects.shadow > 0.,
+        };
+        writeln!(self.out, "{}", element)
+    }
+
+    /// Close the document and give back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        write!(self.out, "{}", Footer(self.effects))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_svg(f, false)
+    }
+}
+
+impl fmt::Display for Themed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write_svg(f, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(frame: &Frame) -> Data {
+        let (w, h) = (frame.w as f64, frame.h as f64);
+        Data::polygon(vec![Pos(0., 0.), Pos(w, 0.), Pos(w, h), Pos(0., h)])
+    }
+
+    #[test]
+    fn themed_colors_survive_without_relative_colors() {
+        let frame = Frame {
+            x: 0,
+            y: 0,
+            w: 20,
+            h: 10,
+            seamless: false,
+        };
+        let base = Color(100, 150, 200);
+        let fill = Color(110, 140, 200);
+        let palette = Palette {
+            themes: vec![base],
+            items: vec![(0, [0; 3])],
+            kinds: Vec::new(),
+        };
+        let mut doc = Document::new(frame).with_palette(palette);
+        doc.add(
+            Path::new(square(&frame))
+                .with_fill_color(fill)
+                .with_stroke_color(fill)
+                .with_tint(Some(Tint {
+                    index: 0,
+                    offset: Some(fill.offset_from(base)),
+                })),
+        );
+        let svg = format!("{}", Themed(&doc));
+        assert!(svg.contains("class=\"tile item-0 tile-edge\""));
+        // resvg does not know relative colors, it must fall back to the attributes
+        let img = render_svg(&svg).unwrap();
+        let px = &img.data()[(5 * 20 + 10) * 4..][..4];
+        assert_eq!(px, &[110, 140, 200, 255]);
+    }
+
+    #[test]
+    fn items_refer_to_their_theme_color() {
+        let frame = Frame {
+            x: 0,
+            y: 0,
+            w: 20,
+            h: 10,
+            seamless: false,
+        };
+        let palette = Palette {
+            themes: vec![Color(10, 20, 30), Color(200, 100, 0)],
+            items: vec![(1, [0; 3]), (0, [5, -5, 0]), (0, [-3, 0, 8])],
+            kinds: vec![25, 0],
+        };
+        let svg = format!("{}", Themed(&Document::new(frame).with_palette(palette)));
+        assert!(svg.contains(
+            "svg { --bg-0: rgb(10,20,30); --bg-1: rgb(200,100,0); --kind-0: 25; --kind-1: 0; }"
+        ));
+        assert!(!svg.contains("--bg-2"));
+        assert!(!svg.contains("--kind-2"));
+        for rule in [
+            ".kind-0 { --kind: var(--kind-0); }",
+            ".kind-1 { --kind: var(--kind-1); }",
+            ".item-0 { --base: rgb(from var(--bg-1) calc(r + 0) calc(g + 0) calc(b + 0)); }",
+            ".item-1 { --base: rgb(from var(--bg-0) calc(r + 5) calc(g + -5) calc(b + 0)); }",
+            ".item-2 { --base: rgb(from var(--bg-0) calc(r + -3) calc(g + 0) calc(b + 8)); }",
+        ] {
+            assert!(svg.contains(rule), "{}", rule);
+        }
+    }
+
+    /// Two background tiles side by side and a pattern tile over the first one,
+    /// rendered by resvg with the given effects
+    fn render_effects(effects: Effects) -> Vec<u8> {
+        let frame = Frame {
+            x: 0,
+            y: 0,
+            w: 60,
+            h: 40,
+            seamless: false,
+        };
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64| {
+            Data::polygon(vec![Pos(x0, y0), Pos(x1, y0), Pos(x1, y1), Pos(x0, y1)])
+        };
+        let tile = |data, c: Color, index| {
+            Path::new(data)
+                .with_fill_color(c)
+                .with_stroke_color(c)
+                .with_stroke_width(0.)
+                .with_tint(Some(Tint {
+                    index,
+                    offset: Some([0; 3]),
+                }))
+        };
+        let mut doc = Document::new(frame).with_effects(effects);
+        doc.add(tile(rect(0., 0., 30., 40.), Color(200, 200, 200), 0));
+        doc.add(tile(rect(30., 0., 60., 40.), Color(100, 100, 100), 0));
+        doc.add(tile(rect(10., 15., 20., 25.), Color(220, 40, 40), 1));
+        render_svg(&format!("{}", doc)).unwrap().data().to_vec()
+    }
+
+    fn px(img: &[u8], x: usize, y: usize) -> [u8; 3] {
+        let i = (y * 60 + x) * 4;
+        [img[i], img[i + 1], img[i + 2]]
+    }
+
+    /// Same color up to the rounding of filter compositing
+    fn close(a: [u8; 3], b: [u8; 3]) -> bool {
+        a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 2)
+    }
+
+    #[test]
+    fn effects_change_the_rendering() {
+        let flat = render_effects(Effects::default());
+        assert_eq!(px(&flat, 29, 5), [200, 200, 200]);
+        assert_eq!(px(&flat, 30, 5), [100, 100, 100]);
+        assert_eq!(px(&flat, 9, 20), [200, 200, 200]);
+        assert_eq!(px(&flat, 11, 16), [220, 40, 40]);
+
+        let grain = render_effects(Effects {
+            grain: 0.5,
+            ..Effects::default()
+        });
+        let mut shades: Vec<_> = (0..30).map(|x| px(&grain, x, 5)).collect();
+        shades.sort_unstable();
+        shades.dedup();
+        assert!(shades.len() > 5, "grain left the tile flat");
+
+        // The whole composition is blurred, the edge between background tiles as well
+        let blur = render_effects(Effects {
+            blur: 2.,
+            ..Effects::default()
+        });
+        assert!(!close(px(&blur, 29, 5), px(&flat, 29, 5)));
+        assert!(!close(px(&blur, 30, 5), px(&flat, 30, 5)));
+        assert!(!close(px(&blur, 9, 20), px(&flat, 9, 20)));
+
+        // Only the pattern is blurred: it bleeds over the background, which stays sharp
+        let blur_pattern = render_effects(Effects {
+            blur_pattern: 2.,
+            ..Effects::default()
+        });
+        assert!(close(px(&blur_pattern, 29, 5), px(&flat, 29, 5)));
+        assert!(close(px(&blur_pattern, 30, 5), px(&flat, 30, 5)));
+        assert!(!close(px(&blur_pattern, 9, 20), px(&flat, 9, 20)));
+
+        // Tiles get darker along their top and left sides, inside the frame
+        let shadow = render_effects(Effects {
+            shadow: 3.,
+            ..Effects::default()
+        });
+        let darker = |a: [u8; 3], b: [u8; 3]| a.iter().zip(&b).all(|(a, b)| a <= b) && !close(a, b);
+        assert!(darker(px(&shadow, 11, 16), px(&flat, 11, 16)));
+        assert!(darker(px(&shadow, 31, 20), px(&flat, 31, 20)));
+        assert!(close(px(&shadow, 50, 35), px(&flat, 50, 35)));
+    }
+}
@@ -1,64 +1,342 @@
+use crate::pos::bounding_box;
 use crate::prelude::*;
 use crate::shape::*;
 use crate::svg::*;
 use delaunator as del;
-use rand::rngs::StdRng;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
 use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::rc::Rc;
 
-macro_rules! set {
-    { $( $elem:expr ),* } => {
-        {
-            let mut set = HashSet::new();
-            $( set.insert($elem); )*
-            set
+/// Receives tiles one at a time, along with their reference position
+pub type TileSink<'a> = dyn FnMut(Pos, Path) + 'a;
+
+/// On a seamless frame, the slight linear distortion (about the center of the frame)
+/// that makes the width and the height of the frame periods of the lattice
+fn lattice_snap(f: &Frame, idir: Pos, jdir: Pos) -> Option<Affine> {
+    let det = idir.0 * jdir.1 - idir.1 * jdir.0;
+    if !f.seamless || det.abs() < 1e-9 {
+        return None;
+    }
+    let (w, h) = (f.w as f64, f.h as f64);
+    let lattice = |(i, j): (f64, f64)| idir * i + jdir * j;
+    let coords = |v: Pos| {
+        (
+            ((v.0 * jdir.1 - v.1 * jdir.0) / det).round(),
+            ((idir.0 * v.1 - idir.1 * v.0) / det).round(),
+        )
+    };
+    // Lattice vectors closest to the sides of the frame, that still span the plane
+    let near = |(i, j): (f64, f64)| {
+        (-2..=2)
+            .flat_map(move |di| (-2..=2).map(move |dj| (i + di as f64, j + dj as f64)))
+            .filter(|&c| c != (0., 0.))
+    };
+    let (ca, cb) = (coords(Pos(w, 0.)), coords(Pos(0., h)));
+    let mut best: Option<(f64, Pos, Pos)> = None;
+    for a in near(ca) {
+        for b in near(cb) {
+            let (u, v) = (lattice(a), lattice(b));
+            if u.0 * v.1 - u.1 * v.0 <= 0. {
+                continue;
+            }
+            let err = u.dist(Pos(w, 0.)) + v.dist(Pos(0., h));
+            if best.is_none_or(|(e, _, _)| err < e) {
+                best = Some((err, u, v));
+            }
+        }
+    }
+    // Linear map that sends `u` to the width and `v` to the height
+    let (_, u, v) = best?;
+    let d = u.0 * v.1 - v.0 * u.1;
+    let m = [w * v.1 / d, -w * v.0 / d, -h * u.1 / d, h * u.0 / d];
+    let c = f.center();
+    Some([
+        m[0],
+        m[1],
+        c.0 - m[0] * c.0 - m[1] * c.1,
+        m[2],
+        m[3],
+        c.1 - m[2] * c.0 - m[3] * c.1,
+    ])
+}
+
+/// Smooth seeded noise that displaces outlines.
+/// The offset only depends on the position, so tiles that share a vertex keep sharing it.
+pub struct Jitter {
+    seed: u64,
+    /// Largest offset along each axis
+    amplitude: f64,
+    /// Distance over which the offset changes direction
+    wavelength: f64,
+    /// Corner and sides of the cells of the noise grid
+    origin: Pos,
+    cell: Pos,
+    /// Number of cells after which the noise repeats along each axis
+    period: Option<(f64, f64)>,
+}
+
+impl Jitter {
+    pub fn new(seed: u64, amplitude: f64, wavelength: f64) -> Self {
+        Self {
+            seed,
+            amplitude,
+            wavelength,
+            origin: Pos(0., 0.),
+            cell: Pos(wavelength, wavelength),
+            period: None,
+        }
+    }
+
+    /// Make the noise repeat with the frame as its period,
+    /// the cells are stretched slightly so that a whole number of them fits
+    pub fn with_period(self, f: &Frame) -> Self {
+        let (w, h) = (f.w as f64, f.h as f64);
+        let nx = (w / self.wavelength).round().max(1.);
+        let ny = (h / self.wavelength).round().max(1.);
+        Self {
+            origin: Pos(f.x as f64, f.y as f64),
+            cell: Pos(w / nx, h / ny),
+            period: Some((nx, ny)),
+            ..self
         }
     }
+
+    /// Displacement of a point: value noise on a grid of side `wavelength`
+    pub fn offset(&self, p: Pos) -> Pos {
+        let x = (p.0 - self.origin.0) / self.cell.0;
+        let y = (p.1 - self.origin.1) / self.cell.1;
+        let (i, j) = (x.floor(), y.floor());
+        let smooth = |t: f64| t * t * (3. - 2. * t);
+        let (tx, ty) = (smooth(x - i), smooth(y - j));
+        let corner = |di: f64, dj: f64| {
+            let (ci, cj) = match self.period {
+                Some((nx, ny)) => ((i + di).rem_euclid(nx), (j + dj).rem_euclid(ny)),
+                None => (i + di, j + dj),
+            };
+            let h = Pos(ci, cj).seeded_hash(self.seed);
+            let unit = |bits: u64| (bits & 0xffff_ffff) as f64 / f64::from(u32::MAX) * 2. - 1.;
+            Pos(unit(h), unit(h >> 32))
+        };
+        let top = corner(0., 0.) * (1. - tx) + corner(1., 0.) * tx;
+        let bottom = corner(0., 1.) * (1. - tx) + corner(1., 1.) * tx;
+        (top * (1. - ty) + bottom * ty) * self.amplitude
+    }
+
+    pub fn apply(&self, p: Pos) -> Pos {
+        p + self.offset(p)
+    }
+
+    /// Displace an outline. Arcs are flattened and sides cut in pieces much shorter
+    /// than the wavelength, so vertices lying on a side of a neighbour stay on it.
+    pub fn distort(&self, data: &Data) -> Data {
+        data.refined(self.wavelength / 4.).mapped(|p| self.apply(p))
+    }
+}
+
+/// How the size of the tiles varies across the frame, as a factor of the nominal size
+pub enum SizeField {
+    /// Factor 1 at the focal point, `ratio` at distance `reach` and beyond
+    Radial { focus: Pos, reach: f64, ratio: f64 },
+    /// Factor 1 at `origin`, `ratio` at distance `length` in direction `dir` and beyond
+    Ramp {
+        origin: Pos,
+        dir: Pos,
+        length: f64,
+        ratio: f64,
+    },
+    /// Factor between 1 and `ratio`, following the first axis of a smooth noise
+    Noise { noise: Jitter, ratio: f64 },
+}
+
+impl SizeField {
+    /// Size factor at a point. Factors are interpolated geometrically,
+    /// so halfway between 1 and 4 tiles are twice as large.
+    pub fn scale(&self, p: Pos) -> f64 {
+        let (t, ratio) = match self {
+            SizeField::Radial {
+                focus,
+                reach,
+                ratio,
+            } => (p.dist(*focus) / reach, ratio),
+            SizeField::Ramp {
+                origin,
+                dir,
+                length,
+                ratio,
+            } => ((p - *origin).dot(*dir) / length, ratio),
+            SizeField::Noise { noise, ratio } => ((noise.offset(p).0 + 1.) / 2., ratio),
+        };
+        ratio.powf(t.clamp(0., 1.))
+    }
+
+    /// Smallest and largest factors
+    pub fn range(&self) -> (f64, f64) {
+        let ratio = match self {
+            SizeField::Radial { ratio, .. }
+            | SizeField::Ramp { ratio, .. }
+            | SizeField::Noise { ratio, .. } => *ratio,
+        };
+        (ratio.min(1.), ratio.max(1.))
+    }
+
+    /// Point that keeps its place when a lattice is stretched to follow the field
+    pub fn center(&self, f: &Frame) -> Pos {
+        match self {
+            SizeField::Radial { focus, .. } => *focus,
+            _ => f.center(),
+        }
+    }
+}
+
+/// Transition of a lattice with the smallest tiles to the size field.
+/// The plane is stretched along each ray from `center` by the size factor met along the way,
+/// relative to the smallest one: tiles grow and shrink smoothly and keep sharing their vertices.
+/// Across the rays the stretch follows the average factor, so tiles are slightly
+/// flattened where the size changes quickly.
+pub struct Stretch<'a> {
+    field: &'a SizeField,
+    center: Pos,
+    /// Integration step, small compared to the frame over which the field varies
+    step: f64,
+    /// Length of the pieces that sides are cut into
+    piece: f64,
+}
+
+impl<'a> Stretch<'a> {
+    pub fn new(f: &Frame, field: &'a SizeField, piece: f64) -> Self {
+        Self {
+            field,
+            center: field.center(f),
+            step: f.w.max(f.h) as f64 / 32.,
+            piece,
+        }
+    }
+
+    /// Image of a point: the distance to the center is integrated with the midpoint method
+    pub fn apply(&self, p: Pos) -> Pos {
+        let r = p.dist(self.center);
+        if r < 1e-9 {
+            return p;
+        }
+        let dir = (p - self.center) * (1. / r);
+        let (min, _) = self.field.range();
+        let rate = |d: f64| self.field.scale(self.center + dir * d) / min;
+        let n = (r / self.step).ceil();
+        let h = r / n;
+        let mut d = 0.;
+        for _ in 0..n as usize {
+            d += h * rate(d + h * rate(d) / 2.);
+        }
+        self.center + dir * d
+    }
+
+    pub fn distort(&self, data: &Data) -> Data {
+        data.refined(self.piece).mapped(|p| self.apply(p))
+    }
 }
 
 /// Tile the plane with a pattern that can be mapped to a 2D grid.
 /// This criterion applies to all tilings used here except Delaunay triangulation.
-fn periodic_grid_tiling<F>(f: &Frame, gen: F, idir: Pos, jdir: Pos) -> Vec<(Pos, Path)>
+/// The range of lattice coordinates is read from the frame bounds expressed in the
+/// `idir`/`jdir` basis, widened by how far the tiles of a period reach from its position.
+/// A period is kept as long as the bounding box of its tiles meets the frame bounds.
+/// Lattices that are degenerate or too fine for the frame produce no tiles.
+fn periodic_grid_tiling<F>(f: &Frame, gen: F, idir: Pos, jdir: Pos, sink: &mut TileSink)
 where
     F: Fn(Pos) -> Vec<(Pos, Path)>,
 {
-    let mut items = Vec::new();
+    let det = idir.0 * jdir.1 - idir.1 * jdir.0;
+    if !det.is_finite() || det.abs() < MIN_PERIOD_AREA {
+        println!("Degenerate lattice {:?} {:?}, no tiles laid", idir, jdir);
+        return;
+    }
+    let snap = lattice_snap(f, idir, jdir);
+    let place = |p: Pos| snap.as_ref().map_or(p, |t| apply(t, p));
     let center = f.center();
-    let mut set = set![center];
-    let mut stk = vec![center];
-    while let Some(pos) = stk.pop() {
-        if f.is_inside(pos) {
-            for item in gen(pos) {
-                items.push(item);
+    // The snap of seamless frames moves positions by less than a period
+    let reach = gen(center)
+        .iter()
+        .map(|(p, _)| p.dist(center))
+        .fold(0., f64::max)
+        + idir.norm()
+        + jdir.norm();
+    let (flo, fhi) = f.bounds();
+    let (lo, hi) = (flo - Pos(reach, reach), fhi + Pos(reach, reach));
+    let coords = |v: Pos| {
+        let v = v - center;
+        (
+            (v.0 * jdir.1 - v.1 * jdir.0) / det,
+            (idir.0 * v.1 - idir.1 * v.0) / det,
+        )
+    };
+    let corners = [lo, Pos(hi.0, lo.1), hi, Pos(lo.0, hi.1)].map(coords);
+    let range = |k: fn(&(f64, f64)) -> f64| {
+        let min = corners.iter().map(k).fold(f64::INFINITY, f64::min);
+        let max = corners.iter().map(k).fold(f64::NEG_INFINITY, f64::max);
+        (min.floor(), max.ceil())
+    };
+    let (imin, imax) = range(|c| c.0);
+    let (jmin, jmax) = range(|c| c.1);
+    if (imax - imin + 1.) * (jmax - jmin + 1.) > MAX_PERIODS as f64 {
+        println!("Lattice too fine for the frame, no tiles laid");
+        return;
+    }
+    for i in imin as i64..=imax as i64 {
+        for j in jmin as i64..=jmax as i64 {
+            let pos = center + idir * i as f64 + jdir * j as f64;
+            if pos.0 < lo.0 || pos.0 > hi.0 || pos.1 < lo.1 || pos.1 > hi.1 {
+                continue;
+            }
+            let mut tiles = gen(pos);
+            if let Some(t) = &snap {
+                for (_, path) in tiles.iter_mut() {
+                    path.data = path.data.mapped(|q| apply(t, q));
+                }
             }
-            for &(i, j) in &[(0, 1), (0, -1), (1, 0), (-1, 0)] {
-                let p = pos + idir * i + jdir * j;
-                if !set.contains(&p) {
-                    set.insert(p);
-                    stk.push(p);
+            // Large tiles can reach into the frame from a period far outside of it
+            let pts: Vec<Pos> = tiles
+                .iter()
+                .flat_map(|(_, path)| path.data.vertices())
+                .collect();
+            let (a, b) = bounding_box(&pts);
+            if a.0 < fhi.0 && b.0 > flo.0 && a.1 < fhi.1 && b.1 > flo.1 {
+                for (p, path) in tiles {
+                    sink(place(p), path);
                 }
             }
         }
     }
-    items
 }
 
-pub fn tile_hexagons(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+/// Smallest area of a period of a lattice, in square pixels
+const MIN_PERIOD_AREA: f64 = 1e-3;
+/// Most lattice positions looked at to cover a frame
+const MAX_PERIODS: usize = 1 << 22;
+
+pub fn tile_hexagons(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let idir = Pos::polar(rot - 30, (size * 2.) * radians(30).cos());
     let jdir = Pos::polar(rot + 30, (size * 2.) * radians(30).cos());
     let m = Movable::hexagon(size, rot);
-    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir)
+    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir, sink)
 }
 
-pub fn tile_triangles(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn tile_triangles(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let idir = Pos::polar(rot - 30, (size * 2.) * radians(30).cos());
     let jdir = Pos::polar(rot + 30, (size * 2.) * radians(30).cos());
     let adjust = Pos::polar(rot + 60, size * radians(30).sin()) + idir * 0.5;
     let m1 = Movable::triangle(size, rot + 60);
     let m2 = Movable::triangle(size, rot);
-    periodic_grid_tiling(f, |p| vec![m1.render(p), m2.render(p + adjust)], idir, jdir)
+    periodic_grid_tiling(
+        f,
+        |p| vec![m1.render(p), m2.render(p + adjust)],
+        idir,
+        jdir,
+        sink,
+    )
 }
 
-pub fn tile_hybrid_hexagons_triangles(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn tile_hybrid_hexagons_triangles(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let idir = Pos::polar(rot, size * 2.);
     let jdir = Pos::polar(rot + 60, size * 2.);
     let adjust = Pos::polar(rot + 30, size / radians(30).cos());
@@ -78,10 +356,11 @@ pub fn tile_hybrid_hexagons_triangles(f: &Frame, size: f64, rot: isize) -> Vec<(
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn tile_hybrid_squares_triangles(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn tile_hybrid_squares_triangles(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let a = size / 2_f64.sqrt();
     let b = a * radians(30).tan();
     let c = a / radians(30).cos();
@@ -126,14 +405,328 @@ pub fn tile_hybrid_squares_triangles(f: &Frame, size: f64, rot: isize) -> Vec<(P
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn tile_rhombus(f: &Frame, ldiag: f64, sdiag: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn tile_rhombus(f: &Frame, ldiag: f64, sdiag: f64, rot: isize, sink: &mut TileSink) {
     let idir = Pos::polar(rot, ldiag) + Pos::polar(rot + 90, sdiag);
     let jdir = Pos::polar(rot, -ldiag) + Pos::polar(rot + 90, sdiag);
     let m = Movable::rhombus(ldiag, sdiag, rot);
-    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir)
+    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir, sink)
+}
+
+/// Tumbling blocks: hexagons split into three rhombi, seen as the faces of a cube.
+/// The face that points up has kind 0, then the left one 1 and the right one 2.
+pub fn tile_cubes(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let idir = Pos::polar(rot - 30, (size * 2.) * radians(30).cos());
+    let jdir = Pos::polar(rot + 30, (size * 2.) * radians(30).cos());
+    // Direction from the center of the hexagon to the far corner of each face
+    let mut faces = (0..3).map(|j| rot + 60 + 120 * j).collect::<Vec<_>>();
+    faces.sort_by(|a, b| radians(*a).sin().partial_cmp(&radians(*b).sin()).unwrap());
+    if radians(faces[1]).cos() > radians(faces[2]).cos() {
+        faces.swap(1, 2);
+    }
+    let faces = faces
+        .into_iter()
+        .map(|a| {
+            let m = Movable::rhombus(size * radians(30).cos(), size / 2., a + 90);
+            (Pos::polar(a, size / 2.), m)
+        })
+        .collect::<Vec<_>>();
+    let gen = |p: Pos| {
+        faces
+            .iter()
+            .enumerate()
+            .map(|(kind, (adjust, m))| {
+                let (pos, path) = m.render(p + *adjust);
+                (pos, path.with_kind(kind))
+            })
+            .collect()
+    };
+    periodic_grid_tiling(f, gen, idir, jdir, sink)
+}
+
+/// Tiling declared in the configuration: prototiles placed within one period
+/// with an optional kind, and the two directions of the period, in units of the tile size
+pub struct CustomTiling {
+    pub tiles: Vec<(Vec<Pos>, Option<usize>)>,
+    pub idir: Pos,
+    pub jdir: Pos,
+}
+
+pub fn tile_custom(f: &Frame, t: &CustomTiling, size: f64, rot: isize, sink: &mut TileSink) {
+    let place = |p: Pos| Pos::polar(rot, p.0 * size) + Pos::polar(rot + 90, p.1 * size);
+    let tiles = t
+        .tiles
+        .iter()
+        .map(|(pts, kind)| {
+            let pts = pts.iter().map(|p| place(*p)).collect::<Vec<_>>();
+            let c = centroid(&pts);
+            (
+                c,
+                Movable::from(pts.into_iter().map(|p| p - c).collect()),
+                *kind,
+            )
+        })
+        .collect::<Vec<_>>();
+    let gen = |p: Pos| {
+        tiles
+            .iter()
+            .map(|(c, m, kind)| {
+                let (pos, path) = m.render(p + *c);
+                match kind {
+                    Some(k) => (pos, path.with_kind(*k)),
+                    None => (pos, path),
+                }
+            })
+            .collect()
+    };
+    periodic_grid_tiling(f, gen, place(t.idir), place(t.jdir), sink)
+}
+
+pub fn tile_squares(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let side = size * 2_f64.sqrt();
+    let idir = Pos::polar(rot, side);
+    let jdir = Pos::polar(rot + 90, side);
+    let m = Movable::square(size, rot);
+    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir, sink)
+}
+
+/// Running bond: rows of 2:1 bricks, each row shifted by half a brick
+pub fn tile_bricks(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let idir = Pos::polar(rot, size * 2.);
+    let jdir = Pos::polar(rot, size) + Pos::polar(rot + 90, size);
+    let m = Movable::rectangle(size * 2., size, rot);
+    periodic_grid_tiling(f, |p| vec![m.render(p)], idir, jdir, sink)
+}
+
+pub fn tile_herringbone(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    // Position in brick units along and across the main direction
+    let at = |a: f64, b: f64| Pos::polar(rot, a * size) + Pos::polar(rot + 90, b * size);
+    //
+    //  +-------+
+    //  |   h   |---+
+    //  +-------+ v |
+    //          |   |
+    //          +---+
+    //
+    // Each staircase step is one horizontal and one vertical brick
+    let idir = at(1., 1.);
+    let jdir = at(-2., 2.);
+    let mv = [
+        Movable::rectangle(size * 2., size, rot),
+        Movable::rectangle(size, size * 2., rot),
+    ];
+    let (hpos, vpos) = (at(-0.75, 0.25), at(0.75, -0.25));
+    periodic_grid_tiling(
+        f,
+        |p| vec![mv[0].render(p + hpos), mv[1].render(p + vpos)],
+        idir,
+        jdir,
+        sink,
+    )
+}
+
+/// Pairs of parallel bricks forming squares, alternating direction as a checkerboard
+pub fn tile_basketweave(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let at = |a: f64, b: f64| Pos::polar(rot, a * size) + Pos::polar(rot + 90, b * size);
+    let idir = at(2., 2.);
+    let jdir = at(2., -2.);
+    let mv = [
+        Movable::rectangle(size * 2., size, rot),
+        Movable::rectangle(size, size * 2., rot),
+    ];
+    let pos = [at(-1., -0.5), at(-1., 0.5), at(0.5, 0.), at(1.5, 0.)];
+    periodic_grid_tiling(
+        f,
+        |p| {
+            vec![
+                mv[0].render(p + pos[0]),
+                mv[0].render(p + pos[1]),
+                mv[1].render(p + pos[2]),
+                mv[1].render(p + pos[3]),
+            ]
+        },
+        idir,
+        jdir,
+        sink,
+    )
+}
+
+/// Archimedean tilings other than the regular ones and the two hybrids,
+/// named after the faces around each vertex
+#[derive(Debug, Clone, Copy)]
+pub enum Archimedean {
+    /// 4.8.8
+    TruncatedSquare,
+    /// 3.12.12
+    TruncatedHexagonal,
+    /// 3.4.6.4
+    Rhombitrihexagonal,
+    /// 4.6.12
+    TruncatedTrihexagonal,
+    /// 3.3.4.3.4
+    SnubSquare,
+    /// 3.3.3.3.6
+    SnubHexagonal,
+    /// 3.3.3.4.4
+    ElongatedTriangular,
+}
+
+impl Archimedean {
+    /// Pick a random tiling of the family
+    pub fn choose(rng: &mut StdRng) -> Self {
+        use Archimedean::*;
+        *[
+            TruncatedSquare,
+            TruncatedHexagonal,
+            Rhombitrihexagonal,
+            TruncatedTrihexagonal,
+            SnubSquare,
+            SnubHexagonal,
+            ElongatedTriangular,
+        ]
+        .choose(rng)
+        .unwrap()
+    }
+
+    /// Faces of one period, with sides of length `size`, and the two directions of the period
+    fn cell(self, size: f64, rot: isize) -> (Vec<(Pos, Movable)>, Pos, Pos) {
+        use Archimedean::*;
+        let dir = |a: isize, r: f64| Pos::polar(rot + a, r * size);
+        let face = |pos: Pos, n: usize, a: isize| (pos, Movable::regular(n, size, rot + a));
+        let sqrt3 = 3_f64.sqrt();
+        match self {
+            TruncatedSquare => {
+                let w = 1. + 2_f64.sqrt();
+                let faces = vec![
+                    face(Pos::zero(), 8, 0),
+                    face(dir(0, w / 2.) + dir(90, w / 2.), 4, 45),
+                ];
+                (faces, dir(0, w), dir(90, w))
+            }
+            TruncatedHexagonal => {
+                let w = 2. + sqrt3;
+                let faces = vec![
+                    face(Pos::zero(), 12, 0),
+                    face((dir(0, w) + dir(60, w)) * (1. / 3.), 3, 90),
+                    face((dir(60, w) + dir(120, w)) * (1. / 3.), 3, 30),
+                ];
+                (faces, dir(0, w), dir(60, w))
+            }
+            Rhombitrihexagonal | TruncatedTrihexagonal => {
+                // Hexagons or dodecagons, with a square between each pair of neighbors
+                let (n, w, holes) = match self {
+                    Rhombitrihexagonal => (6, 1. + sqrt3, [(3, 30), (3, 90)]),
+                    _ => (12, 3. + sqrt3, [(6, 30), (6, 30)]),
+                };
+                let mut faces = vec![face(Pos::zero(), n, 0)];
+                for a in [0, 60, 120] {
+                    faces.push(face(dir(a, w / 2.), 4, a));
+                }
+                faces.push(face(
+                    (dir(0, w) + dir(60, w)) * (1. / 3.),
+                    holes[0].0,
+                    holes[0].1,
+                ));
+                faces.push(face(
+                    (dir(60, w) + dir(120, w)) * (1. / 3.),
+                    holes[1].0,
+                    holes[1].1,
+                ));
+                (faces, dir(0, w), dir(60, w))
+            }
+            SnubSquare => {
+                let w = (2. + sqrt3).sqrt();
+                let mut faces = vec![
+                    face(Pos::zero(), 4, 75),
+                    face(dir(0, w / 2.) + dir(90, w / 2.), 4, 15),
+                ];
+                // Triangles on the sides of the first square
+                for a in [75, 165, 255, 345] {
+                    faces.push(face(dir(a, 0.5 + sqrt3 / 6.), 3, a - 60));
+                }
+                (faces, dir(0, w), dir(90, w))
+            }
+            SnubHexagonal => {
+                // All faces fit on a grid of triangles, one node in seven being a hexagon
+                let up = (dir(0, 1.) + dir(60, 1.)) * (1. / 3.);
+                let mut faces = vec![face(Pos::zero(), 6, 30)];
+                for i in [1., 3., 4., 5.] {
+                    faces.push(face(dir(0, i) + up, 3, 30));
+                }
+                for i in [0., 3., 4., 5.] {
+                    faces.push(face(dir(0, i) + up * 2., 3, 90));
+                }
+                (faces, dir(0, 2.) + dir(60, 1.), dir(0, -1.) + dir(60, 3.))
+            }
+            ElongatedTriangular => {
+                let faces = vec![
+                    face(Pos::zero(), 4, 0),
+                    face(dir(90, 0.5 + sqrt3 / 6.), 3, 30),
+                    face(dir(0, 0.5) + dir(90, 0.5 + sqrt3 / 3.), 3, 90),
+                ];
+                (faces, dir(0, 1.), dir(0, 0.5) + dir(90, 1. + sqrt3 / 2.))
+            }
+        }
+    }
+}
+
+pub fn tile_archimedean(f: &Frame, kind: Archimedean, size: f64, rot: isize, sink: &mut TileSink) {
+    let (faces, idir, jdir) = kind.cell(size, rot);
+    periodic_grid_tiling(
+        f,
+        |pos| faces.iter().map(|(p, m)| m.render(pos + *p)).collect(),
+        idir,
+        jdir,
+        sink,
+    )
+}
+
+/// Dual of an Archimedean tiling: each vertex becomes a tile
+/// whose corners are the centers of the faces around it
+pub fn tile_laves(f: &Frame, kind: Archimedean, size: f64, rot: isize, sink: &mut TileSink) {
+    let (faces, idir, jdir) = kind.cell(size, rot);
+    let det = idir.0 * jdir.1 - idir.1 * jdir.0;
+    // Vertices of one period, brought back into the parallelogram of `idir` and `jdir`
+    let mut vertices: Vec<Pos> = Vec::new();
+    for (p, m) in &faces {
+        for &v in m.vertices() {
+            let v = *p + v;
+            let i = ((v.0 * jdir.1 - v.1 * jdir.0) / det + 1e-6).floor();
+            let j = ((idir.0 * v.1 - idir.1 * v.0) / det + 1e-6).floor();
+            let v = v - idir * i - jdir * j;
+            if !vertices.iter().any(|u| u.dist(v) < size * 1e-3) {
+                vertices.push(v);
+            }
+        }
+    }
+    let tiles = vertices
+        .into_iter()
+        .map(|v| {
+            let mut corners = Vec::new();
+            for i in -2..=2 {
+                for j in -2..=2 {
+                    for (p, m) in &faces {
+                        let p = *p + idir * i + jdir * j;
+                        if m.vertices().iter().any(|&u| (p + u).dist(v) < size * 1e-3) {
+                            corners.push(p - v);
+                        }
+                    }
+                }
+            }
+            corners.sort_by(|a, b| a.1.atan2(a.0).partial_cmp(&b.1.atan2(b.0)).unwrap());
+            (v, Movable::from(corners))
+        })
+        .collect::<Vec<_>>();
+    periodic_grid_tiling(
+        f,
+        |pos| tiles.iter().map(|(v, m)| m.render(pos + *v)).collect(),
+        idir,
+        jdir,
+        sink,
+    )
 }
 
 /// External crate does the heavy lifting and is an order of magnitude faster than the previously implemented Boyer-Watson algorithm.
@@ -155,24 +748,856 @@ fn fast_triangulate(pts: &[Pos]) -> Vec<(Pos, Pos, Pos)> {
     v
 }
 
-pub fn random_delaunay(f: &Frame, rng: &mut StdRng, n: usize) -> Vec<(Pos, Path)> {
+/// Coarse triangles or squares, split in four while their corners and middle
+/// do not all fall in the same scene item (`item_at`).
+/// The finest tiles are a quarter of `size`, the coarsest `2^ADAPTIVE_DEPTH` times larger.
+pub fn tile_adaptive(
+    f: &Frame,
+    size: f64,
+    rot: isize,
+    triangles: bool,
+    item_at: &dyn Fn(Pos) -> usize,
+    sink: &mut TileSink,
+) {
+    let size = size * (1 << ADAPTIVE_DEPTH) as f64 / 4.;
+    let (idir, jdir) = if triangles {
+        (
+            Pos::polar(rot - 30, (size * 2.) * radians(30).cos()),
+            Pos::polar(rot + 30, (size * 2.) * radians(30).cos()),
+        )
+    } else {
+        let side = size * 2_f64.sqrt();
+        (Pos::polar(rot, side), Pos::polar(rot + 90, side))
+    };
+    // The scene is looked up where the tiles end up on a seamless frame
+    let snap = lattice_snap(f, idir, jdir);
+    let place = |p: Pos| snap.as_ref().map_or(p, |t| apply(t, p));
+    let item_at = |p: Pos| item_at(place(p));
+    // Cells are culled by their extent: a coarse cell centered outside
+    // of the frame can still cover part of it
+    let (lo, hi) = f.bounds();
+    let reaches = |pts: &[Pos]| {
+        let (a, b) = bounding_box(&pts.iter().map(|p| place(*p)).collect::<Vec<_>>());
+        a.0 < hi.0 && b.0 > lo.0 && a.1 < hi.1 && b.1 > lo.1
+    };
+    let split = |(p, m): (Pos, Movable)| {
+        let mut tiles = Vec::new();
+        let pts = m.vertices().iter().map(|v| p + *v).collect();
+        subdivide(pts, ADAPTIVE_DEPTH, &item_at, &reaches, &mut tiles);
+        tiles
+    };
+    if triangles {
+        let adjust = Pos::polar(rot + 60, size * radians(30).sin()) + idir * 0.5;
+        let gen = |p: Pos| {
+            let mut tiles = split((p, Movable::triangle(size, rot + 60)));
+            tiles.extend(split((p + adjust, Movable::triangle(size, rot))));
+            tiles
+        };
+        periodic_grid_tiling(f, gen, idir, jdir, sink)
+    } else {
+        let gen = |p: Pos| split((p, Movable::square(size, rot)));
+        periodic_grid_tiling(f, gen, idir, jdir, sink)
+    }
+}
+
+/// Levels of splitting of the adaptive tiling
+const ADAPTIVE_DEPTH: usize = 4;
+
+/// Split a triangle or quadrilateral in four, recursively, where the scene changes.
+/// Cells for which `reaches` fails are dropped.
+fn subdivide(
+    pts: Vec<Pos>,
+    depth: usize,
+    item_at: &dyn Fn(Pos) -> usize,
+    reaches: &dyn Fn(&[Pos]) -> bool,
+    tiles: &mut Vec<(Pos, Path)>,
+) {
+    if !reaches(&pts) {
+        return;
+    }
+    let c = centroid(&pts);
+    let item = item_at(c);
+    if depth == 0 || pts.iter().all(|p| item_at(*p) == item) {
+        tiles.push(Movable::from(pts.iter().map(|p| *p - c).collect()).render(c));
+        return;
+    }
+    let mid = |i: usize| (pts[i] + pts[(i + 1) % pts.len()]) * 0.5;
+    let parts = match pts[..] {
+        [a, b, c] => vec![
+            vec![a, mid(0), mid(2)],
+            vec![mid(0), b, mid(1)],
+            vec![mid(2), mid(1), c],
+            vec![mid(0), mid(1), mid(2)],
+        ],
+        [a, b, d, e] => vec![
+            vec![a, mid(0), c, mid(3)],
+            vec![mid(0), b, mid(1), c],
+            vec![c, mid(1), d, mid(2)],
+            vec![mid(3), c, mid(2), e],
+        ],
+        _ => unreachable!(),
+    };
+    for part in parts {
+        subdivide(part, depth - 1, item_at, reaches, tiles);
+    }
+}
+
+/// Points scattered over the frame, to be triangulated.
+/// A point `p` is kept with probability `scale(p)⁻²`, `scale` being at least 1,
+/// so that the points are further apart where `scale` is large.
+pub fn random_points(
+    f: &Frame,
+    rng: &mut StdRng,
+    n: usize,
+    scale: &dyn Fn(Pos) -> f64,
+) -> Vec<Pos> {
     let mut pts = Vec::new();
-    for _ in 0..n {
-        pts.push(Pos::random(f, rng));
+    while pts.len() < n {
+        let p = Pos::random(f, rng);
+        let s = scale(p);
+        if s > 1. && rng.gen::<f64>() * s * s > 1. {
+            continue;
+        }
+        pts.push(p);
     }
-    let triangulation = fast_triangulate(&pts);
-    triangulation
-        .into_iter()
-        .map(|(a, b, c)| {
+    pts
+}
+
+/// Bridson's Poisson-disk sampling over the frame and its margin.
+/// A point `p` keeps all earlier ones at least `r * scale(p)` away, with `scale` in `(0, 1]`.
+/// Unless `spacing` gives `r`, it is chosen so that about `n` points are placed.
+pub fn poisson_points(
+    f: &Frame,
+    rng: &mut StdRng,
+    n: usize,
+    spacing: Option<f64>,
+    scale: &dyn Fn(Pos) -> f64,
+) -> Vec<Pos> {
+    let scale = |p: Pos| scale(p).clamp(0.1, 1.);
+    let (lo, hi) = f.bounds();
+    let (w, h) = (hi.0 - lo.0, hi.1 - lo.1);
+    let r = spacing.unwrap_or_else(|| {
+        // Average of 1/scale² tells how much the density map adds to a uniform sampling
+        let k = 16;
+        let mut mean = 0.;
+        for i in 0..k {
+            for j in 0..k {
+                let s = scale(
+                    lo + (
+                        w * (i as f64 + 0.5) / k as f64,
+                        h * (j as f64 + 0.5) / k as f64,
+                    ),
+                );
+                mean += 1. / (s * s);
+            }
+        }
+        mean /= (k * k) as f64;
+        (w * h * mean * POISSON_PACKING / n.max(1) as f64).sqrt()
+    });
+    if !r.is_finite() || r <= 0. {
+        return Vec::new();
+    }
+    // Buckets of side `r`: conflicts are never further than one bucket away
+    let (nx, ny) = ((w / r).ceil() as usize + 1, (h / r).ceil() as usize + 1);
+    let bucket = |p: Pos| (((p.0 - lo.0) / r) as usize, ((p.1 - lo.1) / r) as usize);
+    let mut grid = vec![Vec::new(); nx * ny];
+    let first = Pos::random(f, rng);
+    let (bx, by) = bucket(first);
+    grid[by * nx + bx].push(0);
+    let mut pts = vec![first];
+    let mut active = vec![0];
+    while !active.is_empty() {
+        let idx = rng.gen_range(0, active.len());
+        let p = pts[active[idx]];
+        let rp = r * scale(p);
+        let mut found = false;
+        for _ in 0..POISSON_ATTEMPTS {
+            let theta = rng.gen::<f64>() * 2. * PI;
+            let d = rp * (1. + rng.gen::<f64>());
+            let c = p + (d * theta.cos(), d * theta.sin());
+            if c.0 < lo.0 || c.0 >= hi.0 || c.1 < lo.1 || c.1 >= hi.1 {
+                continue;
+            }
+            let rc = r * scale(c);
+            let (bx, by) = bucket(c);
+            let free = (bx.saturating_sub(1)..=(bx + 1).min(nx - 1)).all(|x| {
+                (by.saturating_sub(1)..=(by + 1).min(ny - 1)).all(|y| {
+                    grid[y * nx + x]
+                        .iter()
+                        .all(|&q: &usize| pts[q].dist(c) >= rc)
+                })
+            });
+            if free {
+                grid[by * nx + bx].push(pts.len());
+                active.push(pts.len());
+                pts.push(c);
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            active.swap_remove(idx);
+        }
+    }
+    pts
+}
+
+/// Candidates tried around an active point before it is retired
+const POISSON_ATTEMPTS: usize = 30;
+/// Points per `r²` that Bridson's algorithm reaches with a constant spacing `r`
+const POISSON_PACKING: f64 = 0.65;
+
+/// On a seamless frame, the points that fall in the frame proper are repeated
+/// in the eight neighbouring copies of the frame, so that triangles wrap around the edges.
+pub fn delaunay(f: &Frame, pts: &[Pos], sink: &mut TileSink) {
+    let pts = if f.seamless {
+        periodic_copies(f, &periodic_sites(f, pts))
+    } else {
+        pts.to_vec()
+    };
+    for (a, b, c) in fast_triangulate(&pts) {
+        let center = (a + b + c) * (1. / 3.);
+        if f.seamless && !f.is_inside(center) {
+            continue;
+        }
+        sink(
+            center,
+            Path::new(Data::new(a).with_line_to(b).with_line_to(c)),
+        );
+    }
+}
+
+/// Points that lie in the frame proper, the margin is left to their copies
+fn periodic_sites(f: &Frame, pts: &[Pos]) -> Vec<Pos> {
+    pts.iter().copied().filter(|&p| f.wrap(p) == p).collect()
+}
+
+/// The points and their copies shifted by the width and height of the frame.
+/// The unshifted points come first, in the same order.
+fn periodic_copies(f: &Frame, pts: &[Pos]) -> Vec<Pos> {
+    let (w, h) = (f.w as f64, f.h as f64);
+    let mut res = pts.to_vec();
+    for &(i, j) in &[
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+    ] {
+        res.extend(pts.iter().map(|&p| p + Pos(w * i as f64, h * j as f64)));
+    }
+    res
+}
+
+/// Voronoi cells of the points, clipped to the frame (margin included).
+/// Each relaxation step moves the points to the centroid of their cell (Lloyd's algorithm),
+/// which evens out cell sizes.
+/// On a seamless frame, the sites are those in the frame proper along with their copies
+/// around it, and relaxation brings the centroids back into the frame.
+pub fn voronoi(f: &Frame, pts: &[Pos], relax: usize, sink: &mut TileSink) {
+    let mut pts = if f.seamless {
+        periodic_sites(f, pts)
+    } else {
+        pts.to_vec()
+    };
+    let sites = |pts: &[Pos]| {
+        if f.seamless {
+            periodic_copies(f, pts)
+        } else {
+            pts.to_vec()
+        }
+    };
+    for _ in 0..relax {
+        pts = voronoi_cells(f, &sites(&pts))
+            .iter()
+            .zip(pts.iter())
+            .map(|(cell, &p)| {
+                if cell.len() < 3 {
+                    p
+                } else {
+                    f.wrap(centroid(cell))
+                }
+            })
+            .collect();
+    }
+    let pts = sites(&pts);
+    for (cell, p) in voronoi_cells(f, &pts).into_iter().zip(pts) {
+        if cell.len() >= 3 && f.is_inside(p) {
+            sink(p, Path::new(Data::polygon(cell)));
+        }
+    }
+}
+
+/// The cell of a point is the frame cut by the bisectors with each of its Delaunay neighbours
+fn voronoi_cells(f: &Frame, pts: &[Pos]) -> Vec<Vec<Pos>> {
+    let points = pts
+        .iter()
+        .map(|&Pos(x, y)| del::Point { x, y })
+        .collect::<Vec<_>>();
+    let mut neighbours = vec![Vec::new(); pts.len()];
+    for t in del::triangulate(&points).triangles.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (t[k], t[(k + 1) % 3]);
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+    }
+    let (lo, hi) = f.bounds();
+    pts.iter()
+        .zip(neighbours.iter_mut())
+        .map(|(&p, near)| {
+            near.sort_unstable();
+            near.dedup();
+            let mut cell = vec![lo, Pos(hi.0, lo.1), hi, Pos(lo.0, hi.1)];
+            for &q in near.iter() {
+                let q = pts[q];
+                cell = clip_half_plane(&cell, (p + q) * 0.5, q - p);
+            }
+            cell
+        })
+        .collect()
+}
+
+/// Part of a convex polygon on the side of `limit` opposite to the direction `normal`
+fn clip_half_plane(poly: &[Pos], limit: Pos, normal: Pos) -> Vec<Pos> {
+    let side = |p: Pos| (p - limit).dot(normal);
+    let mut res = Vec::new();
+    for i in 0..poly.len() {
+        let (a, b) = (poly[i], poly[(i + 1) % poly.len()]);
+        let (sa, sb) = (side(a), side(b));
+        if sa <= 0. {
+            res.push(a);
+        }
+        if (sa < 0. && sb > 0.) || (sa > 0. && sb < 0.) {
+            res.push(a + (b - a) * (sa / (sa - sb)));
+        }
+    }
+    res
+}
+
+const PHI: f64 = 1.618_033_988_749_895;
+
+/// Truchet motifs, each tile picks one of a few orientations
+#[derive(Debug, Clone, Copy)]
+pub enum Truchet {
+    /// Squares with quarter circles around two opposite corners
+    Arcs,
+    /// Squares cut in two along a diagonal
+    Diagonals,
+    /// Hexagons with arcs around every other corner
+    Hexagons,
+}
+
+impl Truchet {
+    /// Pick a random motif
+    pub fn choose(rng: &mut StdRng) -> Self {
+        use Truchet::*;
+        *[Arcs, Diagonals, Hexagons].choose(rng).unwrap()
+    }
+}
+
+/// Truchet tiling: `orient` tells the orientation of the tile centered at a position
+/// (any value, taken modulo the number of orientations of the motif).
+/// The two regions of each motif have different kinds.
+pub fn tile_truchet(
+    f: &Frame,
+    style: Truchet,
+    size: f64,
+    rot: isize,
+    orient: &dyn Fn(Pos) -> usize,
+    sink: &mut TileSink,
+) {
+    let emit = |data: Data, kind: usize| {
+        let pts = data.flatten();
+        (centroid(&pts), Path::new(data).with_kind(kind))
+    };
+    let (n, first, idir, jdir) = match style {
+        Truchet::Hexagons => {
+            let step = (size * 2.) * radians(30).cos();
             (
-                (a + b + c) * 0.33,
-                Path::new(Data::new(a).with_line_to(b).with_line_to(c)),
+                6,
+                rot,
+                Pos::polar(rot - 30, step),
+                Pos::polar(rot + 30, step),
             )
+        }
+        _ => {
+            let side = size * 2_f64.sqrt();
+            (
+                4,
+                rot + 45,
+                Pos::polar(rot, side),
+                Pos::polar(rot + 90, side),
+            )
+        }
+    };
+    let snap = lattice_snap(f, idir, jdir);
+    let gen = |p: Pos| {
+        let o = orient(snap.as_ref().map_or(p, |t| apply(t, p)));
+        let v = |k: usize| p + Pos::polar(first + (360 / n * k) as isize, size);
+        // Middle of the side that starts at corner `k`
+        let mid = |k: usize| (v(k) + v(k + 1)) * 0.5;
+        let s = o % 2;
+        match style {
+            Truchet::Diagonals => {
+                let kind = o / 2 % 2;
+                let half = |k: usize| Data::polygon(vec![v(k), v(k + 1), v(k + 2)]);
+                vec![emit(half(s), kind), emit(half(s + 2), 1 - kind)]
+            }
+            _ => {
+                // Sectors around every other corner, and what remains between them
+                let mut tiles = Vec::new();
+                let mut rest = Data::new(mid(s));
+                for c in (s..s + n).step_by(2) {
+                    let sector = Data::new(v(c))
+                        .with_line_to(mid(c))
+                        .with_arc_to(v(c), mid(c + n - 1));
+                    tiles.push(emit(sector, 0));
+                    rest.line_to(v(c + 1));
+                    rest.line_to(mid(c + 1));
+                    rest.arc_to(v(c + 2), mid(c + 2));
+                }
+                tiles.push(emit(rest, 1));
+                tiles
+            }
+        }
+    };
+    periodic_grid_tiling(f, gen, idir, jdir, sink)
+}
+
+/// Half of a Penrose tile: a golden triangle (angles 36-72-72) when `acute`,
+/// a golden gnomon (angles 108-36-36) otherwise. The apex is always `a`.
+#[derive(Clone, Copy)]
+struct Robinson {
+    acute: bool,
+    a: Pos,
+    b: Pos,
+    c: Pos,
+}
+
+impl Robinson {
+    fn new(acute: bool, a: Pos, b: Pos, c: Pos) -> Self {
+        Self { acute, a, b, c }
+    }
+
+    /// Smaller triangles that pair up across their `a`-`c` edge into kites and darts
+    fn deflate_kites(self, out: &mut Vec<Self>) {
+        let Self { acute, a, b, c } = self;
+        if acute {
+            let p = b + (a - b) * (1. / PHI);
+            let q = c + (a - c) * (1. / PHI / PHI);
+            out.push(Self::new(true, b, c, q));
+            out.push(Self::new(true, b, p, q));
+            out.push(Self::new(false, p, q, a));
+        } else {
+            let u = c + (b - c) * (1. / PHI);
+            out.push(Self::new(true, c, u, a));
+            out.push(Self::new(false, u, a, b));
+        }
+    }
+
+    /// Smaller triangles that pair up across their `b`-`c` edge into thin and thick rhombs
+    fn deflate_rhombs(self, out: &mut Vec<Self>) {
+        let Self { acute, a, b, c } = self;
+        if acute {
+            let p = a + (b - a) * (1. / PHI);
+            out.push(Self::new(true, c, p, b));
+            out.push(Self::new(false, p, c, a));
+        } else {
+            let q = b + (a - b) * (1. / PHI);
+            let r = b + (c - b) * (1. / PHI);
+            out.push(Self::new(false, r, c, a));
+            out.push(Self::new(false, q, r, b));
+            out.push(Self::new(true, r, q, a));
+        }
+    }
+
+    /// Whole tile made of this half and its mirror image
+    fn tile(self, kites: bool) -> Vec<Pos> {
+        let Self { a, b, c, .. } = self;
+        if kites {
+            let axis = (c - a).unit();
+            let mirror = a + axis * (2. * (b - a).dot(axis)) - (b - a);
+            vec![a, b, c, mirror]
+        } else {
+            vec![a, b, b + c - a, c]
+        }
+    }
+}
+
+/// Aperiodic Penrose tiling, either kites and darts (P2) or thin and thick rhombs (P3).
+/// A sun of 10 triangles large enough to cover the frame is deflated until the tiles reach
+/// the requested size, dropping along the way the triangles that fall outside.
+/// Tiles are tagged with their kind: 0 for kites and thin rhombs, 1 for darts and thick rhombs.
+pub fn penrose(f: &Frame, size: f64, kites: bool, rot: isize, sink: &mut TileSink) {
+    let center = f.center();
+    let (lo, hi) = f.bounds();
+    let mut side = (hi - lo).norm() / 2. / radians(18).cos();
+    let mut halves = (0..10)
+        .map(|i| {
+            let b = center + Pos::polar(rot + 36 * i - 18, side);
+            let c = center + Pos::polar(rot + 36 * i + 18, side);
+            if i % 2 == 0 {
+                Robinson::new(true, center, c, b)
+            } else {
+                Robinson::new(true, center, b, c)
+            }
         })
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>();
+    let overlaps = |t: &Robinson| {
+        let xs = [t.a.0, t.b.0, t.c.0];
+        let ys = [t.a.1, t.b.1, t.c.1];
+        xs.iter().any(|&x| x >= lo.0)
+            && xs.iter().any(|&x| x <= hi.0)
+            && ys.iter().any(|&y| y >= lo.1)
+            && ys.iter().any(|&y| y <= hi.1)
+    };
+    while side > size * 2. && size > 0. {
+        let mut next = Vec::new();
+        for t in halves {
+            if kites {
+                t.deflate_kites(&mut next);
+            } else {
+                t.deflate_rhombs(&mut next);
+            }
+        }
+        next.retain(overlaps);
+        halves = next;
+        side /= PHI;
+    }
+    let mut seen = HashSet::new();
+    for t in halves {
+        let pts = t.tile(kites);
+        let mid = centroid(&pts);
+        if f.is_inside(mid) && seen.insert(mid) {
+            sink(
+                mid,
+                Path::new(Data::polygon(pts)).with_kind(usize::from(!t.acute)),
+            );
+        }
+    }
+}
+
+/// Affine map `[a, b, c, d, e, f]` sending `(x, y)` to `(a x + b y + c, d x + e y + f)`
+type Affine = [f64; 6];
+
+fn apply(t: &Affine, p: Pos) -> Pos {
+    Pos(
+        t[0] * p.0 + t[1] * p.1 + t[2],
+        t[3] * p.0 + t[4] * p.1 + t[5],
+    )
+}
+
+fn compose(a: &Affine, b: &Affine) -> Affine {
+    [
+        a[0] * b[0] + a[1] * b[3],
+        a[0] * b[1] + a[1] * b[4],
+        a[0] * b[2] + a[1] * b[5] + a[2],
+        a[3] * b[0] + a[4] * b[3],
+        a[3] * b[1] + a[4] * b[4],
+        a[3] * b[2] + a[4] * b[5] + a[5],
+    ]
+}
+
+/// Rotation by `a` radians around `p`
+fn rotation_about(p: Pos, a: f64) -> Affine {
+    let (c, s) = (a.cos(), a.sin());
+    [
+        c,
+        -s,
+        p.0 - c * p.0 + s * p.1,
+        s,
+        c,
+        p.1 - s * p.0 - c * p.1,
+    ]
+}
+
+/// Similarity sending `p1` to `p2` and `q1` to `q2`
+fn match_two(p1: Pos, q1: Pos, p2: Pos, q2: Pos) -> Affine {
+    let seg = |p: Pos, q: Pos| [q.0 - p.0, p.1 - q.1, p.0, q.1 - p.1, q.0 - p.0, p.1];
+    let t = seg(p1, q1);
+    let det = t[0] * t[4] - t[1] * t[3];
+    let inv = [
+        t[4] / det,
+        -t[1] / det,
+        (t[1] * t[5] - t[2] * t[4]) / det,
+        -t[3] / det,
+        t[0] / det,
+        (t[2] * t[3] - t[0] * t[5]) / det,
+    ];
+    compose(&seg(p2, q2), &inv)
+}
+
+/// Intersection of the lines `p1`-`q1` and `p2`-`q2`
+fn line_crossing(p1: Pos, q1: Pos, p2: Pos, q2: Pos) -> Pos {
+    let (d1, d2) = (q1 - p1, q2 - p2);
+    let t = ((p2.0 - p1.0) * d2.1 - (p2.1 - p1.1) * d2.0) / (d1.0 * d2.1 - d1.1 * d2.0);
+    p1 + d1 * t
+}
+
+/// Position on a grid of hexagons of side 1
+fn hex_pos(x: f64, y: f64) -> Pos {
+    Pos(x + 0.5 * y, y * 3_f64.sqrt() / 2.)
+}
+
+/// Outline of the hat, made of 8 kites of the hexagonal grid
+fn hat_outline() -> Vec<Pos> {
+    [
+        (0., 0.),
+        (-1., -1.),
+        (0., -2.),
+        (2., -2.),
+        (2., -1.),
+        (4., -2.),
+        (5., -1.),
+        (4., 0.),
+        (3., 0.),
+        (2., 2.),
+        (0., 3.),
+        (0., 2.),
+        (-1., 2.),
+    ]
+    .iter()
+    .map(|&(x, y)| hex_pos(x, y))
+    .collect()
 }
 
-pub fn pentagons_type1(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+/// Part of a metatile: either a single hat or a smaller metatile
+enum Piece {
+    Hat,
+    Meta(Rc<Metatile>),
+}
+
+/// One of the four metatiles H, T, P and F of the hat tiling, at some level of substitution
+struct Metatile {
+    outline: Vec<Pos>,
+    children: Vec<(Affine, Piece)>,
+}
+
+impl Metatile {
+    fn new(outline: Vec<Pos>) -> Self {
+        Self {
+            outline,
+            children: Vec::new(),
+        }
+    }
+
+    fn shape(piece: &Piece) -> Vec<Pos> {
+        match piece {
+            Piece::Hat => hat_outline(),
+            Piece::Meta(m) => m.outline.clone(),
+        }
+    }
+
+    /// Vertex `i` of child `n`, in the coordinates of the metatile
+    fn child_vertex(&self, n: usize, i: usize) -> Pos {
+        let (t, piece) = &self.children[n];
+        let shape = Self::shape(piece);
+        apply(t, shape[i % shape.len()])
+    }
+
+    /// Move the origin to the center of the outline
+    fn recenter(mut self) -> Self {
+        let mid = self.outline.iter().fold(Pos::zero(), |acc, p| acc + *p)
+            * (1. / self.outline.len() as f64);
+        self.outline = self.outline.iter().map(|&p| p - mid).collect();
+        let shift = [1., 0., -mid.0, 0., 1., -mid.1];
+        for (t, _) in self.children.iter_mut() {
+            *t = compose(&shift, t);
+        }
+        self
+    }
+
+    /// The four metatiles before any substitution, in the order H, T, P, F
+    fn initial() -> [Rc<Self>; 4] {
+        let hat = hat_outline();
+        let r3 = 3_f64.sqrt() / 2.;
+        let mut h = Self::new(vec![
+            Pos(0., 0.),
+            Pos(4., 0.),
+            Pos(4.5, r3),
+            Pos(2.5, 5. * r3),
+            Pos(1.5, 5. * r3),
+            Pos(-0.5, r3),
+        ]);
+        let o = h.outline.clone();
+        h.children = vec![
+            (match_two(hat[5], hat[7], o[5], o[0]), Piece::Hat),
+            (match_two(hat[9], hat[11], o[1], o[2]), Piece::Hat),
+            (match_two(hat[5], hat[7], o[3], o[4]), Piece::Hat),
+            // The only reflected hat
+            (
+                compose(
+                    &[-0.5, -r3, 2.5, r3, -0.5, r3],
+                    &[0.5, 0., 0., 0., -0.5, 0.],
+                ),
+                Piece::Hat,
+            ),
+        ];
+        let mut t = Self::new(vec![Pos(0., 0.), Pos(3., 0.), Pos(1.5, 3. * r3)]);
+        t.children = vec![([0.5, 0., 0.5, 0., 0.5, r3], Piece::Hat)];
+        // P and F only differ by their outline
+        let pair = || {
+            vec![
+                ([0.5, 0., 1.5, 0., 0.5, r3], Piece::Hat),
+                (
+                    compose(
+                        &[0.5, r3, 0., -r3, 0.5, 2. * r3],
+                        &[0.5, 0., 0., 0., 0.5, 0.],
+                    ),
+                    Piece::Hat,
+                ),
+            ]
+        };
+        let mut p = Self::new(vec![
+            Pos(0., 0.),
+            Pos(4., 0.),
+            Pos(3., 2. * r3),
+            Pos(-1., 2. * r3),
+        ]);
+        p.children = pair();
+        let mut f = Self::new(vec![
+            Pos(0., 0.),
+            Pos(3., 0.),
+            Pos(3.5, r3),
+            Pos(3., 2. * r3),
+            Pos(-1., 2. * r3),
+        ]);
+        f.children = pair();
+        [h, t, p, f].map(|m| Rc::new(m.recenter()))
+    }
+
+    /// One step of substitution: a patch of metatiles is assembled,
+    /// then cut into larger copies of the four metatiles
+    fn substitute(tiles: &[Rc<Self>; 4]) -> [Rc<Self>; 4] {
+        const H: usize = 0;
+        const T: usize = 1;
+        const P: usize = 2;
+        const F: usize = 3;
+        // Each new metatile has its side `t` glued from vertex `b` of tile `j`
+        // to vertex `a` of tile `i`, tiles being numbered in order of addition.
+        #[rustfmt::skip]
+        const RULES: [(usize, usize, usize, usize, usize, usize); 28] = [
+            (0, 0, 0, 1, P, 2), (1, 0, 1, 1, H, 2), (2, 0, 2, 1, P, 2), (3, 0, 3, 1, H, 2),
+            (4, 4, 4, 5, P, 2), (0, 4, 0, 5, F, 3), (2, 4, 2, 5, F, 3), (4, 1, 3, 2, F, 0),
+            (8, 3, 8, 4, H, 0), (9, 2, 9, 3, P, 0), (10, 2, 10, 3, H, 0), (11, 4, 11, 5, P, 2),
+            (12, 0, 12, 1, H, 2), (13, 0, 13, 1, F, 3), (14, 2, 14, 3, F, 1), (15, 3, 15, 4, H, 4),
+            (8, 2, 8, 3, F, 1), (17, 3, 17, 4, H, 0), (18, 2, 18, 3, P, 0), (19, 2, 19, 3, H, 2),
+            (20, 4, 20, 5, F, 3), (20, 0, 20, 1, P, 2), (22, 0, 22, 1, H, 2), (23, 4, 23, 5, F, 3),
+            (23, 0, 23, 1, F, 3), (16, 0, 16, 1, P, 2), (9, 4, 0, 2, T, 2), (4, 0, 4, 1, F, 3),
+        ];
+        let mut patch = Self::new(Vec::new());
+        patch
+            .children
+            .push(([1., 0., 0., 0., 1., 0.], Piece::Meta(tiles[H].clone())));
+        for &(i, a, j, b, kind, t) in RULES.iter() {
+            let (p, q) = (patch.child_vertex(j, b), patch.child_vertex(i, a));
+            let shape = &tiles[kind].outline;
+            let m = match_two(shape[t], shape[(t + 1) % shape.len()], p, q);
+            patch.children.push((m, Piece::Meta(tiles[kind].clone())));
+        }
+
+        let v = |n, i| patch.child_vertex(n, i);
+        let sixth = rotation_about(Pos::zero(), -PI / 3.);
+        let bps1 = v(8, 2);
+        let bps2 = v(21, 2);
+        let rbps = apply(&rotation_about(bps1, -2. * PI / 3.), bps2);
+        let p72 = v(7, 2);
+        let p252 = v(25, 2);
+        let llc = line_crossing(bps1, rbps, v(6, 2), p72);
+        let w = apply(&sixth, v(6, 2) - llc);
+        let h2 = bps1 + w;
+        let h3 = v(14, 2);
+        let h4 = h3 - apply(&sixth, w);
+        let h_outline = vec![llc, bps1, h2, h3, h4, v(6, 2)];
+        let p_outline = vec![p72, p72 + bps1 - llc, bps1, llc];
+        let f_outline = vec![bps2, v(24, 2), v(25, 0), p252, p252 + llc - bps1];
+        let b = bps1 + h4 - v(6, 2);
+        let t_outline = vec![b, apply(&rotation_about(b, -PI / 3.), h2), h2];
+
+        let mut children = patch.children.into_iter().map(Some).collect::<Vec<_>>();
+        let mut pick = |outline, idx: &[usize]| {
+            let mut m = Self::new(outline);
+            m.children = idx.iter().filter_map(|&i| children[i].take()).collect();
+            Rc::new(m.recenter())
+        };
+        let h = pick(h_outline, &[0, 9, 16, 27, 26, 6, 1, 8, 10, 15]);
+        let t = pick(t_outline, &[11]);
+        let p = pick(p_outline, &[7, 2, 3, 4, 28]);
+        let f = pick(f_outline, &[21, 20, 22, 23, 24, 25]);
+        [h, t, p, f]
+    }
+
+    /// Send the hats to `sink`, skipping the metatiles too far from the frame
+    fn emit(&self, f: &Frame, t: &Affine, margin: f64, sink: &mut TileSink) {
+        let (lo, hi) = f.bounds();
+        for (m, piece) in &self.children {
+            let m = compose(t, m);
+            match piece {
+                Piece::Hat => {
+                    let pts = hat_outline()
+                        .into_iter()
+                        .map(|p| apply(&m, p))
+                        .collect::<Vec<_>>();
+                    let mid = centroid(&pts);
+                    if f.is_inside(mid) {
+                        let reflected = m[0] * m[4] - m[1] * m[3] < 0.;
+                        sink(
+                            mid,
+                            Path::new(Data::polygon(pts)).with_kind(usize::from(reflected)),
+                        );
+                    }
+                }
+                Piece::Meta(meta) => {
+                    let pts = meta
+                        .outline
+                        .iter()
+                        .map(|&p| apply(&m, p))
+                        .collect::<Vec<_>>();
+                    if pts.iter().any(|p| p.0 > lo.0 - margin)
+                        && pts.iter().any(|p| p.0 < hi.0 + margin)
+                        && pts.iter().any(|p| p.1 > lo.1 - margin)
+                        && pts.iter().any(|p| p.1 < hi.1 + margin)
+                    {
+                        meta.emit(f, &m, margin, sink);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Aperiodic tiling by the hat monotile, generated by substitution of its metatiles.
+/// A metatile is substituted until it covers the frame; hats have the area of a hexagon of radius `size`.
+/// Tiles are tagged with their kind: 1 for the reflected hats, 0 for the others.
+pub fn tile_hats(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let scale = size * 3_f64.sqrt() / 2.;
+    let (lo, hi) = f.bounds();
+    let radius = (hi - lo).norm() / 2. / scale;
+    let mut tiles = Metatile::initial();
+    // Distance from the center of the H metatile to its outline
+    let inner = |m: &Metatile| {
+        let n = m.outline.len();
+        (0..n)
+            .map(|i| {
+                let (a, b) = (m.outline[i], m.outline[(i + 1) % n]);
+                ((b - a).0 * a.1 - (b - a).1 * a.0).abs() / (b - a).norm()
+            })
+            .fold(f64::INFINITY, f64::min)
+    };
+    while inner(&tiles[0]) < radius && size > 0. {
+        tiles = Metatile::substitute(&tiles);
+    }
+    let (c, s) = (radians(rot).cos() * scale, radians(rot).sin() * scale);
+    let center = f.center();
+    let t = [c, -s, center.0, s, c, center.1];
+    tiles[0].emit(f, &t, size * 4., sink);
+}
+
+pub fn pentagons_type1(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let beta = 80;
     let gamma = 180 - beta;
     let alpha = 110;
@@ -197,10 +1622,11 @@ pub fn pentagons_type1(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn pentagons_type2(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn pentagons_type2(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let alpha = 110;
     let beta = 110;
     let gamma = 100;
@@ -230,10 +1656,11 @@ pub fn pentagons_type2(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn pentagons_type3(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn pentagons_type3(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let alpha = 120;
     let gamma = alpha;
     let delta = alpha;
@@ -261,10 +1688,11 @@ pub fn pentagons_type3(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn pentagons_type4(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn pentagons_type4(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let alpha = 100;
     let beta = 90;
     let gamma = 130;
@@ -293,10 +1721,11 @@ pub fn pentagons_type4(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn pentagons_type5(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
+pub fn pentagons_type5(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
     let alpha = 150;
     let beta = 60;
     let gamma = 120;
@@ -349,10 +1778,11 @@ pub fn pentagons_type5(f: &Frame, size: f64, rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
     )
 }
 
-pub fn pentagons_type6(f: &Frame, size: f64, _rot: isize) -> Vec<(Pos, Path)> {
+pub fn pentagons_type6(f: &Frame, size: f64, _rot: isize, sink: &mut TileSink) {
     let rot = 60;
     let alpha = 150;
     let beta = 44;
@@ -382,6 +1812,300 @@ pub fn pentagons_type6(f: &Frame, size: f64, _rot: isize) -> Vec<(Pos, Path)> {
         },
         idir,
         jdir,
+        sink,
+    )
+}
+
+pub fn pentagons_type7(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let gamma = 140.;
+    let delta = 360. - 2. * gamma;
+    let beta = 108.747_400_849_477;
+    let epsilon = (360. - beta) / 2.;
+    let alpha = 540. - beta - gamma - delta - epsilon;
+    let sizes = [size, size, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 0, true, 4),
+            (0, 2, false, 2),
+            (0, 4, false, 1),
+            (0, 3, false, 2),
+            (1, 2, true, 2),
+            (1, 3, true, 0),
+            (2, 4, true, 3),
+        ],
+        &[(0, 1, 4, 0), (4, 3, 0, 2)],
+        &[(0, 2, 2, 2), (2, 0, 5, 4), (5, 3, 1, 1), (1, 0, 0, 4)],
+        sink,
+    )
+}
+
+pub fn pentagons_type8(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let beta = 125.;
+    let gamma = 360. - 2. * beta;
+    let delta = 89.688_155_221_507;
+    let epsilon = (360. - delta) / 2.;
+    let alpha = 540. - beta - gamma - delta - epsilon;
+    let sizes = [size, size, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 0, true, 4),
+            (0, 2, false, 0),
+            (0, 4, false, 3),
+            (0, 3, true, 0),
+            (0, 1, false, 2),
+            (1, 4, true, 2),
+            (1, 2, true, 3),
+        ],
+        &[(0, 0, 1, 4), (1, 3, 4, 2), (4, 1, 0, 2)],
+        &[(0, 2, 2, 0), (2, 3, 3, 0), (3, 4, 0, 3)],
+        sink,
+    )
+}
+
+pub fn pentagons_type9(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 110.;
+    let gamma = 360. - 2. * alpha;
+    let delta = 64.812_632_478_179;
+    let epsilon = (360. - delta) / 2.;
+    let beta = 540. - alpha - gamma - delta - epsilon;
+    let sizes = [size, size, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 0, true, 4),
+            (0, 1, false, 1),
+            (0, 2, false, 1),
+            (0, 3, true, 0),
+            (1, 3, true, 3),
+            (2, 4, true, 1),
+            (3, 1, false, 1),
+        ],
+        &[(0, 2, 3, 1), (3, 4, 0, 3)],
+        &[(0, 3, 4, 0), (4, 3, 6, 3), (6, 2, 2, 3), (2, 2, 0, 0)],
+        sink,
+    )
+}
+
+pub fn pentagons_type10(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 90.;
+    let beta = 100.;
+    let gamma = (360. - beta) / 2.;
+    let epsilon = 180. - beta;
+    let delta = (360. - epsilon) / 2.;
+    let sizes = [size, size * 0.368_767_004_711, size * 0.688_240_662_962];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 3, false, 2),
+            (0, 0, false, 0),
+            (0, 1, false, 4),
+            (0, 2, true, 4),
+            (1, 2, true, 4),
+        ],
+        &[(0, 0, 2, 0), (2, 0, 1, 0), (1, 3, 0, 2)],
+        &[(0, 1, 3, 4), (3, 3, 5, 1), (5, 4, 0, 3)],
+        sink,
+    )
+}
+
+pub fn pentagons_type11(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 90.;
+    let gamma = 66.;
+    let beta = (360. - gamma) / 2.;
+    let epsilon = 180. - gamma;
+    let delta = 540. - alpha - beta - gamma - epsilon;
+    let sizes = [size * 1.461_512_159_061, size * 0.346_624_619_532, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 1, true, 3),
+            (0, 3, false, 3),
+            (0, 4, false, 3),
+            (1, 1, true, 1),
+            (1, 3, true, 2),
+            (2, 2, false, 1),
+            (4, 1, true, 0),
+        ],
+        &[(0, 1, 1, 3), (1, 1, 4, 1), (4, 4, 2, 0), (2, 4, 0, 2)],
+        &[(0, 1, 1, 3), (1, 2, 3, 1), (3, 4, 0, 3)],
+        sink,
+    )
+}
+
+pub fn pentagons_type12(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 90.;
+    let gamma = 66.;
+    let beta = (360. - gamma) / 2.;
+    let epsilon = 180. - gamma;
+    let delta = 540. - alpha - beta - gamma - epsilon;
+    let sizes = [size * 1.085_315_193_159, size * 0.380_604_230_694, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 1, true, 3),
+            (0, 3, false, 3),
+            (0, 4, false, 3),
+            (1, 1, true, 1),
+            (1, 2, false, 4),
+            (3, 1, true, 3),
+            (4, 1, true, 0),
+        ],
+        &[(0, 2, 3, 1), (3, 4, 0, 3)],
+        &[(0, 3, 2, 3), (2, 1, 4, 3), (4, 2, 5, 1), (5, 3, 0, 4)],
+        sink,
+    )
+}
+
+pub fn pentagons_type13(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 100.;
+    let beta = 90.;
+    let delta = 360. - 2. * alpha;
+    let epsilon = 90.;
+    let gamma = 540. - alpha - beta - delta - epsilon;
+    let sizes = [size * 1.390_387_540_685, size * 0.405_579_787_673, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 1, true, 3),
+            (0, 3, false, 2),
+            (0, 0, false, 3),
+            (0, 2, true, 2),
+            (1, 1, true, 4),
+            (2, 0, false, 3),
+            (3, 2, true, 2),
+        ],
+        &[(0, 1, 1, 3), (1, 2, 0, 3)],
+        &[(0, 2, 4, 2), (4, 1, 7, 4), (7, 3, 3, 1), (3, 4, 0, 4)],
+        sink,
+    )
+}
+
+pub fn pentagons_type14(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 90.;
+    let gamma = 69.323_327_476_993;
+    let beta = (360. - gamma) / 2.;
+    let epsilon = 180. - gamma;
+    let delta = 540. - alpha - beta - gamma - epsilon;
+    let sizes = [size * 1.346_850_246_733, size * 0.5, size];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 1, true, 3),
+            (0, 3, false, 3),
+            (0, 4, false, 3),
+            (1, 0, false, 2),
+            (1, 3, true, 2),
+        ],
+        &[(0, 3, 2, 3), (2, 1, 4, 0), (4, 0, 1, 1), (1, 4, 0, 0)],
+        &[(0, 4, 3, 3), (3, 1, 5, 3), (5, 2, 0, 1)],
+        sink,
+    )
+}
+
+pub fn pentagons_type15(f: &Frame, size: f64, rot: isize, sink: &mut TileSink) {
+    let alpha = 150.;
+    let beta = 60.;
+    let gamma = 135.;
+    let delta = 105.;
+    let epsilon = 90.;
+    let sizes = [size * 2., size, size * 1.931_851_652_578];
+    let angles = [alpha, beta, gamma, delta, epsilon];
+    glued_pentagons(
+        f,
+        &Movable::pentagon(angles, sizes),
+        rot,
+        &[
+            (0, 1, true, 3),
+            (0, 3, false, 2),
+            (0, 0, false, 1),
+            (1, 1, true, 4),
+            (2, 1, true, 3),
+            (2, 0, false, 1),
+            (3, 0, false, 3),
+            (4, 3, true, 4),
+            (5, 1, true, 4),
+            (6, 0, false, 3),
+            (8, 2, true, 1),
+        ],
+        &[(0, 3, 2, 2), (2, 2, 0, 1)],
+        &[
+            (0, 1, 1, 3),
+            (1, 1, 4, 4),
+            (4, 3, 8, 4),
+            (8, 2, 11, 1),
+            (11, 4, 10, 0),
+            (10, 4, 6, 4),
+            (6, 2, 2, 4),
+            (2, 3, 0, 2),
+        ],
+        sink,
+    )
+}
+
+/// Periodic tiling by copies of a single pentagon and of its mirror image.
+/// Each `(i, a, mirrored, b)` of `glue` adds a tile with its vertex `b` on vertex `a` of tile `i`
+/// (tile 0 being the pentagon itself), which makes up one period.
+/// `idir` and `jdir` are walks from tile 0 to its next copies, one `(i, a, j, b)` per step
+/// from a tile oriented like tile `i` to one oriented like tile `j`.
+fn glued_pentagons(
+    f: &Frame,
+    pentagon: &Movable,
+    rot: isize,
+    glue: &[(usize, usize, bool, usize)],
+    idir: &[(usize, usize, usize, usize)],
+    jdir: &[(usize, usize, usize, usize)],
+    sink: &mut TileSink,
+) {
+    let mirror = pentagon.mirrored();
+    let mut mv = vec![pentagon.rotated(rot as f64)];
+    let mut offsets = vec![Pos::zero()];
+    for &(i, a, mirrored, b) in glue {
+        let tile = mv[i].glue(a, if mirrored { &mirror } else { pentagon }, b);
+        offsets.push(offsets[i] + mv[i].vertex(a) - tile.vertex(b));
+        mv.push(tile);
+    }
+    let walk = |steps: &[(usize, usize, usize, usize)]| {
+        steps.iter().fold(Pos::zero(), |acc, &(i, a, j, b)| {
+            acc + mv[i].vertex(a) - mv[j].vertex(b)
+        })
+    };
+    periodic_grid_tiling(
+        f,
+        |pos| {
+            mv.iter()
+                .zip(&offsets)
+                .map(|(m, &o)| m.render(pos + o))
+                .collect()
+        },
+        walk(idir),
+        walk(jdir),
+        sink,
     )
 }
 
@@ -417,3 +2141,335 @@ impl Pentagon {
         Movable::from(pts.into_iter().map(|p| p - mid).collect::<Vec<_>>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pos::polygon_contains;
+    use rand::SeedableRng;
+
+    /// Slivers thinner than this are rounding errors of the tile angles
+    const TOL: f64 = 0.05;
+
+    const FRAME: Frame = Frame {
+        x: 0,
+        y: 0,
+        w: 240,
+        h: 180,
+        seamless: false,
+    };
+
+    type Tiler<'a> = Box<dyn Fn(&mut TileSink) + 'a>;
+
+    /// Distance from `p` to the segment `[a, b]`
+    fn to_segment(p: Pos, a: Pos, b: Pos) -> f64 {
+        let ab = b - a;
+        let t = ((p - a).dot(ab) / ab.dot(ab)).clamp(0., 1.);
+        p.dist(a + ab * t)
+    }
+
+    /// Sample points of the frame covered by no tile and by several tiles.
+    /// Points on a side are left out.
+    fn gaps_and_overlaps(f: &Frame, tiler: &dyn Fn(&mut TileSink)) -> (usize, usize) {
+        let mut tiles = Vec::new();
+        tiler(&mut |_, path| {
+            let pts = path.data.flatten();
+            tiles.push((bounding_box(&pts), pts));
+        });
+        let (mut gaps, mut overlaps) = (0, 0);
+        for i in 0..f.w / 2 {
+            for j in 0..f.h / 2 {
+                let p = Pos(
+                    f.x as f64 + 2. * i as f64 + 0.37,
+                    f.y as f64 + 2. * j as f64 + 0.71,
+                );
+                let near = tiles.iter().filter(|((lo, hi), _)| {
+                    lo.0 <= p.0 && p.0 <= hi.0 && lo.1 <= p.1 && p.1 <= hi.1
+                });
+                let mut count = 0;
+                let mut on_side = false;
+                for (_, pts) in near {
+                    let n = pts.len();
+                    on_side |= (0..n).any(|k| to_segment(p, pts[k], pts[(k + 1) % n]) < TOL);
+                    count += polygon_contains(pts, p) as usize;
+                }
+                match count {
+                    _ if on_side => (),
+                    0 => gaps += 1,
+                    1 => (),
+                    _ => overlaps += 1,
+                }
+            }
+        }
+        (gaps, overlaps)
+    }
+
+    #[test]
+    fn periodic_tilings_cover_the_frame_once() {
+        use Archimedean::*;
+        let f = &FRAME;
+        let (size, rot) = (15., 17);
+        let mut tilers: Vec<(String, Tiler)> = vec![
+            (
+                "hexagons".into(),
+                Box::new(|s| tile_hexagons(f, size, rot, s)),
+            ),
+            (
+                "triangles".into(),
+                Box::new(|s| tile_triangles(f, size, rot, s)),
+            ),
+            (
+                "squares and triangles".into(),
+                Box::new(|s| tile_hybrid_squares_triangles(f, size, rot, s)),
+            ),
+            (
+                "rhombus".into(),
+                Box::new(|s| tile_rhombus(f, size, size * 0.6, rot, s)),
+            ),
+            ("cubes".into(), Box::new(|s| tile_cubes(f, size, rot, s))),
+            (
+                "squares".into(),
+                Box::new(|s| tile_squares(f, size, rot, s)),
+            ),
+            ("bricks".into(), Box::new(|s| tile_bricks(f, size, rot, s))),
+            (
+                "herringbone".into(),
+                Box::new(|s| tile_herringbone(f, size, rot, s)),
+            ),
+            (
+                "basketweave".into(),
+                Box::new(|s| tile_basketweave(f, size, rot, s)),
+            ),
+        ];
+        for kind in [
+            TruncatedSquare,
+            TruncatedHexagonal,
+            Rhombitrihexagonal,
+            TruncatedTrihexagonal,
+            SnubSquare,
+            SnubHexagonal,
+            ElongatedTriangular,
+        ] {
+            tilers.push((
+                format!("{:?}", kind),
+                Box::new(move |s| tile_archimedean(f, kind, size, rot, s)),
+            ));
+            tilers.push((
+                format!("{:?} dual", kind),
+                Box::new(move |s| tile_laves(f, kind, size, rot, s)),
+            ));
+        }
+        let pentagons: [fn(&Frame, f64, isize, &mut TileSink); 15] = [
+            pentagons_type1,
+            pentagons_type2,
+            pentagons_type3,
+            pentagons_type4,
+            pentagons_type5,
+            pentagons_type6,
+            pentagons_type7,
+            pentagons_type8,
+            pentagons_type9,
+            pentagons_type10,
+            pentagons_type11,
+            pentagons_type12,
+            pentagons_type13,
+            pentagons_type14,
+            pentagons_type15,
+        ];
+        for (n, tiler) in pentagons.into_iter().enumerate() {
+            tilers.push((
+                format!("pentagons type {}", n + 1),
+                Box::new(move |s| tiler(f, size, rot, s)),
+            ));
+        }
+        let failed: Vec<_> = tilers
+            .iter()
+            .map(|(name, tiler)| (name, gaps_and_overlaps(f, tiler.as_ref())))
+            .filter(|(_, holes)| *holes != (0, 0))
+            .collect();
+        assert!(failed.is_empty(), "gaps and overlaps: {:?}", failed);
+    }
+
+    #[test]
+    fn adaptive_tiles_cover_the_frame_once() {
+        let f = &FRAME;
+        // A disc and a slanted band, so that cells split along curved and straight edges
+        let item_at = |p: Pos| {
+            if p.dist(Pos(100., 80.)) < 50. {
+                1
+            } else if (p.0 + 2. * p.1) % 150. < 40. {
+                2
+            } else {
+                0
+            }
+        };
+        for triangles in [false, true] {
+            for rot in [0, 17] {
+                let tiler = |s: &mut TileSink| tile_adaptive(f, 6., rot, triangles, &item_at, s);
+                assert_eq!(
+                    gaps_and_overlaps(f, &tiler),
+                    (0, 0),
+                    "triangles: {}, rotation: {}",
+                    triangles,
+                    rot
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn voronoi_cells_cover_the_frame_once() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let pts = random_points(&FRAME, &mut rng, 300, &|_| 1.);
+        for relax in [0, 3] {
+            let tiler = |s: &mut TileSink| voronoi(&FRAME, &pts, relax, s);
+            assert_eq!(
+                gaps_and_overlaps(&FRAME, &tiler),
+                (0, 0),
+                "relax: {}",
+                relax
+            );
+        }
+    }
+
+    #[test]
+    fn seamless_voronoi_cells_match_across_edges() {
+        let f = &Frame {
+            seamless: true,
+            ..FRAME
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let pts = random_points(f, &mut rng, 300, &|_| 1.);
+        let (w, h) = (f.w as f64, f.h as f64);
+        // Distance on the torus the seamless image is wrapped around
+        let torus = |a: Pos, b: Pos| {
+            let d = a - b;
+            let (dx, dy) = (d.0 - w * (d.0 / w).round(), d.1 - h * (d.1 / h).round());
+            (dx * dx + dy * dy).sqrt()
+        };
+        for relax in [0, 3] {
+            let tiler = |s: &mut TileSink| voronoi(f, &pts, relax, s);
+            assert_eq!(gaps_and_overlaps(f, &tiler), (0, 0), "relax: {}", relax);
+            // Cells past an edge are copies of the cells at the opposite edge:
+            // each point lies in the cell of the nearest site around the torus
+            let mut cells = Vec::new();
+            tiler(&mut |p, path| cells.push((p, path.data.flatten())));
+            let sites: Vec<_> = cells.iter().map(|(p, _)| f.wrap(*p)).collect();
+            for i in 0..f.w / 3 {
+                for j in 0..f.h / 3 {
+                    let x = Pos(3. * i as f64 + 0.37, 3. * j as f64 + 0.71);
+                    let nearest = sites.iter().map(|&s| torus(x, s)).fold(f64::MAX, f64::min);
+                    for (p, _) in cells.iter().filter(|(_, c)| polygon_contains(c, x)) {
+                        assert!(torus(x, *p) < nearest + TOL, "relax: {}, at {:?}", relax, x);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hats_cover_the_frame_once() {
+        let f = &FRAME;
+        // Twice the signed area, its sign tells the orientation of the outline
+        let orientation = |pts: &[Pos]| {
+            let n = pts.len();
+            (0..n)
+                .map(|k| {
+                    let (a, b) = (pts[k], pts[(k + 1) % n]);
+                    a.0 * b.1 - a.1 * b.0
+                })
+                .sum::<f64>()
+                .signum()
+        };
+        let hat = orientation(&hat_outline());
+        for rot in [0, 17] {
+            let tiler = |s: &mut TileSink| tile_hats(f, 8., rot, s);
+            assert_eq!(gaps_and_overlaps(f, &tiler), (0, 0), "rotation: {}", rot);
+            // Reflected hats are the ones whose outline is turned the other way round
+            let mut counts = [0; 2];
+            tiler(&mut |_, path| {
+                let kind = path.kind.unwrap();
+                let reflected = orientation(&path.data.flatten()) != hat;
+                assert_eq!(kind, usize::from(reflected));
+                counts[kind] += 1;
+            });
+            assert!(0 < counts[1] && counts[1] < counts[0], "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn poisson_samples_keep_their_distance() {
+        let f = &FRAME;
+        let r = 9.;
+        let center = f.center();
+        // Shaped like the densities of the configuration: towards the middle, along an edge
+        let uniform = |_| 1.;
+        let towards_center = |p: Pos| 0.35 + 0.65 * (p.dist(center) / 150.).min(1.);
+        let along_edge = |p: Pos| {
+            if (p.dist(center) - 50.).abs() < 15. {
+                0.35
+            } else {
+                1.
+            }
+        };
+        let scales: [(&str, &dyn Fn(Pos) -> f64); 3] = [
+            ("uniform", &uniform),
+            ("center", &towards_center),
+            ("boundaries", &along_edge),
+        ];
+        let (lo, hi) = f.bounds();
+        for (name, scale) in &scales {
+            let mut rng = StdRng::seed_from_u64(3);
+            let pts = poisson_points(f, &mut rng, 0, Some(r), *scale);
+            assert!(pts.len() > 400, "{}: {} points", name, pts.len());
+            for (i, &p) in pts.iter().enumerate() {
+                assert!(lo.0 <= p.0 && p.0 < hi.0 && lo.1 <= p.1 && p.1 < hi.1);
+                // Each sample was placed at its own radius from the earlier ones
+                let rp = r * scale(p);
+                let near = pts[..i].iter().find(|q| q.dist(p) < rp);
+                assert!(near.is_none(), "{}: {:?} and {:?}", name, p, near);
+            }
+        }
+    }
+
+    #[test]
+    fn poisson_sampling_is_deterministic() {
+        let sample = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            poisson_points(&FRAME, &mut rng, 400, None, &|p| 0.5 + p.0 / 600.)
+                .into_iter()
+                .map(|Pos(x, y)| (x, y))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sample(5), sample(5));
+        assert_ne!(sample(5), sample(6));
+    }
+
+    #[test]
+    fn truchet_tiles_cover_the_frame_once() {
+        let f = &FRAME;
+        // Orientations that change from one tile to the next without any regularity
+        let orient = |p: Pos| (p.0 * 7.3 + p.1 * 13.7).rem_euclid(97.) as usize;
+        for style in [Truchet::Arcs, Truchet::Diagonals, Truchet::Hexagons] {
+            for rot in [0, 17] {
+                let tiler = |s: &mut TileSink| tile_truchet(f, style, 12., rot, &orient, s);
+                assert_eq!(
+                    gaps_and_overlaps(f, &tiler),
+                    (0, 0),
+                    "{:?}, rotation: {}",
+                    style,
+                    rot
+                );
+                // Arcs are flattened into many short sides, not replaced by their chord
+                let mut most = 0;
+                tiler(&mut |_, path| most = most.max(path.data.flatten().len()));
+                assert_eq!(
+                    most > 8,
+                    !matches!(style, Truchet::Diagonals),
+                    "{:?}",
+                    style
+                );
+            }
+        }
+    }
+}